@@ -1,7 +1,21 @@
+pub mod taocket_binary;
+pub mod taocket_bindings;
+pub mod taocket_cancel;
+pub mod taocket_capability;
+pub mod taocket_channel;
+pub mod taocket_command;
 pub mod taocket_config;
 pub mod taocket_context;
+pub mod taocket_event;
+pub mod taocket_geometry;
 pub mod taocket_hotkey;
+pub mod taocket_ipc;
+pub mod taocket_legacy;
+pub mod taocket_limits;
 pub mod taocket_macro;
+pub mod taocket_middleware;
+pub mod taocket_multiwindow;
+pub mod taocket_native_events;
 pub mod taocket_protocol;
 pub mod taocket_utils;
 pub mod taocket_window;
@@ -1,12 +1,96 @@
 (function () {
-	let initialX = 0;
-	let initialY = 0;
+	const RESIZE_CURSORS = {
+		n: "ns-resize",
+		s: "ns-resize",
+		e: "ew-resize",
+		w: "ew-resize",
+		ne: "nesw-resize",
+		sw: "nesw-resize",
+		nw: "nwse-resize",
+		se: "nwse-resize",
+	};
+
+	// Cursors for data-taocket-resize regions and the automatic border, which
+	// marks the root element with a taocket-resize-<edge> class
+	const style = document.createElement("style");
+	style.textContent = Object.entries(RESIZE_CURSORS)
+		.map(
+			([edge, cursor]) =>
+				`[data-taocket-resize="${edge}"] { cursor: ${cursor}; }\n` +
+				`html.taocket-resize-${edge}, html.taocket-resize-${edge} * { cursor: ${cursor} !important; }`,
+		)
+		.join("\n");
+	if (document.head) document.head.append(style);
+	else document.addEventListener("DOMContentLoaded", () => document.head.append(style));
+
+	// Edge of the invisible resize border under the pointer, if any; the
+	// border is off while the window is framed, maximized or not resizable
+	function borderEdge(e) {
+		const border = window.__TAOCKET_RESIZE_BORDER__ || 0;
+		if (border <= 0 || !window.__TAOCKET_RESIZE_ENABLED__) return null;
+		const vertical = e.clientY < border ? "n" : e.clientY >= window.innerHeight - border ? "s" : "";
+		const horizontal = e.clientX < border ? "w" : e.clientX >= window.innerWidth - border ? "e" : "";
+		return vertical + horizontal || null;
+	}
+
+	function resizeEdge(e) {
+		const region = e.target.closest && e.target.closest("[data-taocket-resize]");
+		const edge = region && region.getAttribute("data-taocket-resize");
+		if (edge in RESIZE_CURSORS) return edge;
+		return borderEdge(e);
+	}
+
+	let borderCursor = null;
+	document.addEventListener("mousemove", (e) => {
+		const edge = e.buttons === 0 ? borderEdge(e) : borderCursor;
+		if (edge === borderCursor) return;
+		const root = document.documentElement;
+		if (borderCursor) root.classList.remove(`taocket-resize-${borderCursor}`);
+		if (edge) root.classList.add(`taocket-resize-${edge}`);
+		borderCursor = edge;
+	});
+
+	// Controls that stay clickable inside a drag region
+	const INTERACTIVE = "button, a[href], input, select, textarea, label, [contenteditable]";
+
+	// Whether the pointer is on a drag region: the closest element with
+	// data-taocket-drag-region decides, and data-taocket-drag-region="false"
+	// or an interactive element in between opts out
+	function inDragRegion(target) {
+		if (!target.closest) return false;
+		const region = target.closest("[data-taocket-drag-region]");
+		if (!region || region.getAttribute("data-taocket-drag-region") === "false") return false;
+		const control = target.closest(INTERACTIVE);
+		return !control || !region.contains(control) || control === region;
+	}
+
+	function doubleClick() {
+		switch (window.__TAOCKET_DOUBLE_CLICK__ || "maximize") {
+			case "maximize":
+				return taocket.window.toggleMaximize();
+			case "minimize":
+				return taocket.window.minimize();
+		}
+	}
+
 	document.addEventListener("mousedown", (e) => {
-		const attr = e.target.getAttribute("data-taocket-drag-region");
-		if (attr != null && (e.detail === 1 || e.detail === 2)) {
+		const edge = e.button === 0 && resizeEdge(e);
+		if (edge) {
+			e.preventDefault();
+			e.stopImmediatePropagation();
+			taocket.window.startResizing(edge);
+			return;
+		}
+
+		if (e.button !== 0 || !inDragRegion(e.target)) return;
+		if (e.detail === 1) {
+			e.preventDefault();
+			e.stopImmediatePropagation();
+			taocket.window.startDragging();
+		} else if (e.detail === 2) {
 			e.preventDefault();
 			e.stopImmediatePropagation();
-			invoke("Move", true);
+			doubleClick();
 		}
 	});
 })();
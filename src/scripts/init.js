@@ -1,17 +1,443 @@
 (function () {
 	if (window.__API__INVOKE) return;
+	// Filled in by the Rust side; must match `taocket_ipc::PROTOCOL_VERSION`
+	const PROTOCOL_VERSION = __TAOCKET_PROTOCOL__;
 	let nextId = 1;
-	window.__API__INVOKE = function invoke(event, value) {
-		return new Promise((resolve, reject) => {
-			const id = nextId++;
-			const message = JSON.stringify(
-				{ payload: { id, event: { type: event, value } } },
-				null,
-				2,
+	const pending = new Map();
+	// Batch id -> ids of its calls still pending
+	const batches = new Map();
+	const channels = new Map();
+	const listeners = new Map();
+
+	class TaocketError extends Error {
+		constructor(error) {
+			super(error && error.message ? error.message : String(error));
+			this.name = "TaocketError";
+			if (error && typeof error === "object") Object.assign(this, error);
+		}
+	}
+
+	function showProtocolError(message) {
+		console.error(message);
+		const show = () => {
+			if (document.getElementById("taocket-protocol-error")) return;
+			const banner = document.createElement("div");
+			banner.id = "taocket-protocol-error";
+			banner.setAttribute("role", "alert");
+			banner.style.cssText =
+				"position:fixed;top:0;left:0;right:0;z-index:2147483647;padding:8px 12px;" +
+				"background:#b00020;color:#fff;font:13px/1.4 system-ui,sans-serif;";
+			banner.textContent = message;
+			document.body.appendChild(banner);
+		};
+		if (document.body) show();
+		else document.addEventListener("DOMContentLoaded", show, { once: true });
+	}
+
+	window.__TAOCKET__ = {
+		protocol: PROTOCOL_VERSION,
+		// Called by generated clients with the version they were built for
+		checkVersion(version) {
+			if (version === PROTOCOL_VERSION) return true;
+			showProtocolError(
+				`taocket client was generated for protocol v${version} but the page runs v${PROTOCOL_VERSION}; regenerate the bindings`,
+			);
+			return false;
+		},
+		settle(response) {
+			// The backend only answers a batch id when rejecting the whole batch
+			const members = batches.get(response.id);
+			if (members) {
+				batches.delete(response.id);
+				if (response.status !== "Ok") {
+					for (const id of [...members]) this.settle({ ...response, id });
+				}
+				return;
+			}
+			const call = pending.get(response.id);
+			if (!call) return;
+			pending.delete(response.id);
+			call.cleanup();
+			const channel = channels.get(response.id);
+			if (response.status === "Ok") {
+				call.resolve(response.data);
+				// A call that settled without opening a channel never will
+				if (channel && !channel.opened) closeChannel(response.id);
+			} else {
+				channels.delete(response.id);
+				call.reject(new TaocketError(response.data));
+			}
+		},
+		settleAll(responses) {
+			for (const response of responses) this.settle(response);
+		},
+		emit(topic, payload) {
+			const callbacks = listeners.get(topic);
+			if (!callbacks) return;
+			for (const callback of [...callbacks]) {
+				try {
+					callback(payload);
+				} catch (e) {
+					console.error(`taocket listener for "${topic}" failed`, e);
+				}
+			}
+		},
+		channel(id, event) {
+			const channel = channels.get(id);
+			if (!channel) return;
+			switch (event.type) {
+				case "Open":
+					channel.opened = true;
+					break;
+				case "Message":
+					if (channel.onMessage) channel.onMessage(event.data);
+					break;
+				case "Close":
+					closeChannel(id);
+					break;
+			}
+		},
+	};
+
+	function closeChannel(id) {
+		const channel = channels.get(id);
+		if (!channel) return;
+		channels.delete(id);
+		if (channel.onClose) channel.onClose();
+	}
+
+	function send(kind, id, event, value) {
+		window.ipc.postMessage(
+			JSON.stringify({ kind, payload: { id, event: { type: event, value } } }, null, 2),
+		);
+	}
+
+	// Stop waiting for call `id` and tell Rust so its cancel token fires
+	function abandon(id, kind, message, event) {
+		channels.delete(id);
+		const call = pending.get(id);
+		if (!call) return;
+		pending.delete(id);
+		call.cleanup();
+		try {
+			send("cancel", id, "Cancel");
+		} catch (_) {}
+		call.reject(new TaocketError({ kind, message, event }));
+	}
+
+	// Register a pending call and return its id, or null if already aborted.
+	// `options`: `channel` ({ onMessage, onClose }), `timeout` in ms, `signal`
+	function track(event, options, resolve, reject) {
+		const { channel, timeout, signal } = options;
+		if (signal && signal.aborted) {
+			reject(new TaocketError({ kind: "Cancelled", message: `'${event}' was cancelled`, event }));
+			return null;
+		}
+		const id = nextId++;
+		let timer = null;
+		const onAbort = () => abandon(id, "Cancelled", `'${event}' was cancelled`, event);
+		const cleanup = () => {
+			if (timer !== null) clearTimeout(timer);
+			if (signal) signal.removeEventListener("abort", onAbort);
+		};
+		pending.set(id, { resolve, reject, cleanup });
+		if (channel) channels.set(id, { opened: false, ...channel });
+		if (timeout > 0) {
+			timer = setTimeout(
+				() => abandon(id, "Timeout", `'${event}' timed out after ${timeout}ms`, event),
+				timeout,
 			);
-			window.ipc.postMessage(message);
+		}
+		if (signal) signal.addEventListener("abort", onAbort, { once: true });
+		return id;
+	}
+
+	function fail(id, error) {
+		const call = pending.get(id);
+		if (!call) return;
+		pending.delete(id);
+		channels.delete(id);
+		call.cleanup();
+		call.reject(error);
+	}
+
+	function post(kind, event, value, options = {}) {
+		return new Promise((resolve, reject) => {
+			const id = track(event, options, resolve, reject);
+			if (id === null) return;
+			try {
+				send(kind, id, event, value);
+			} catch (e) {
+				fail(id, e);
+			}
+		});
+	}
+
+	// Send several invokes in one message; returns one promise per call.
+	// `calls` holds `[event, value]` pairs or `{ event, value }` objects
+	function batch(calls, options = {}) {
+		const batchId = nextId++;
+		const members = new Set();
+		const messages = [];
+		const promises = calls.map((call) => {
+			const [event, value] = Array.isArray(call) ? call : [call.event, call.value];
+			return new Promise((resolve, reject) => {
+				const id = track(event, options, resolve, reject);
+				if (id === null) return;
+				const tracked = pending.get(id);
+				const cleanup = tracked.cleanup;
+				tracked.cleanup = () => {
+					cleanup();
+					members.delete(id);
+					if (!members.size) batches.delete(batchId);
+				};
+				members.add(id);
+				messages.push({ kind: "invoke", payload: { id, event: { type: event, value } } });
+			});
+		});
+		if (messages.length) {
+			batches.set(batchId, members);
+			try {
+				send("batch", batchId, "Batch", messages);
+			} catch (e) {
+				for (const message of messages) fail(message.payload.id, e);
+			}
+		}
+		return promises;
+	}
+
+	// `options`: `onMessage`/`onClose` receive messages from a Rust `Channel`,
+	// `timeout` (ms) and `signal` (AbortSignal) abandon the call
+	window.__API__INVOKE = function invoke(event, value, options = {}) {
+		const channel =
+			options.onMessage || options.onClose
+				? { onMessage: options.onMessage, onClose: options.onClose }
+				: undefined;
+		return post("invoke", event, value, {
+			channel,
+			timeout: options.timeout,
+			signal: options.signal,
 		});
 	};
 
+	// Async iterator over the messages a command sends through its channel;
+	// leaving the loop early cancels the call
+	function stream(event, value, options = {}) {
+		const queue = [];
+		let done = false;
+		let failure = null;
+		let wake = null;
+		const notify = () => {
+			if (wake) wake();
+			wake = null;
+		};
+		const channel = {
+			onMessage(data) {
+				queue.push(data);
+				notify();
+			},
+			onClose() {
+				done = true;
+				notify();
+			},
+		};
+		const controller = new AbortController();
+		if (options.signal) {
+			if (options.signal.aborted) controller.abort();
+			else options.signal.addEventListener("abort", () => controller.abort(), { once: true });
+		}
+		post("invoke", event, value, {
+			channel,
+			timeout: options.timeout,
+			signal: controller.signal,
+		}).catch((e) => {
+			failure = e;
+			done = true;
+			notify();
+		});
+		return {
+			[Symbol.asyncIterator]() {
+				return this;
+			},
+			async next() {
+				while (!queue.length && !done) {
+					await new Promise((resolve) => (wake = resolve));
+				}
+				if (queue.length) return { value: queue.shift(), done: false };
+				if (failure) throw failure;
+				return { value: undefined, done: true };
+			},
+			async return() {
+				done = true;
+				queue.length = 0;
+				controller.abort();
+				return { value: undefined, done: true };
+			},
+		};
+	}
+
+	// Windows and Android expose custom protocols as http(s)://<scheme>.localhost
+	const PROTOCOL_ORIGIN = /Windows/.test(navigator.userAgent)
+		? "http://taocket.localhost"
+		: /Android/.test(navigator.userAgent)
+			? "https://taocket.localhost"
+			: "taocket://localhost";
+
+	// POST raw bytes to a binary command; resolves with the fetch Response.
+	// `options`: `headers`, `contentType`, `timeout` in ms, `signal`
+	async function binary(command, body, options = {}) {
+		const controller = new AbortController();
+		let timedOut = false;
+		const timer =
+			options.timeout > 0
+				? setTimeout(() => {
+						timedOut = true;
+						controller.abort();
+					}, options.timeout)
+				: null;
+		const onAbort = () => controller.abort();
+		if (options.signal) {
+			if (options.signal.aborted) controller.abort();
+			else options.signal.addEventListener("abort", onAbort, { once: true });
+		}
+		const headers = { ...options.headers };
+		if (options.contentType) headers["Content-Type"] = options.contentType;
+		try {
+			const response = await fetch(
+				`${PROTOCOL_ORIGIN}/__taocket/ipc/${encodeURIComponent(command)}`,
+				{ method: "POST", headers, body, signal: controller.signal },
+			);
+			if (!response.ok) {
+				let error;
+				try {
+					error = await response.json();
+				} catch (_) {
+					error = { message: `Binary command '${command}' failed with ${response.status}` };
+				}
+				throw new TaocketError({ event: command, ...error });
+			}
+			return response;
+		} catch (e) {
+			if (e && e.name === "AbortError") {
+				throw timedOut
+					? new TaocketError({
+							kind: "Timeout",
+							message: `'${command}' timed out after ${options.timeout}ms`,
+							event: command,
+						})
+					: new TaocketError({ kind: "Cancelled", message: `'${command}' was cancelled`, event: command });
+			}
+			throw e;
+		} finally {
+			if (timer !== null) clearTimeout(timer);
+			if (options.signal) options.signal.removeEventListener("abort", onAbort);
+		}
+	}
+
+	function listen(topic, callback) {
+		if (!listeners.has(topic)) listeners.set(topic, new Set());
+		listeners.get(topic).add(callback);
+		return () => unlisten(topic, callback);
+	}
+
+	function unlisten(topic, callback) {
+		const callbacks = listeners.get(topic);
+		if (!callbacks) return;
+		callbacks.delete(callback);
+		if (!callbacks.size) listeners.delete(topic);
+	}
+
+	// Resolves once the Rust listeners for `topic` have run
+	function emit(topic, payload) {
+		return post("emit", topic, payload);
+	}
+
+	const builtin = (event) => (value) => post("window", event, value);
+
+	// beforeclose listeners; the backend only asks the page while there are any
+	const closeListeners = new Set();
+	function onBeforeClose(listener) {
+		closeListeners.add(listener);
+		if (closeListeners.size === 1) post("window", "GuardClose", true);
+		return () => {
+			if (closeListeners.delete(listener) && closeListeners.size === 0) {
+				post("window", "GuardClose", false);
+			}
+		};
+	}
+	// Called by the backend on a close request; any listener returning (or
+	// resolving to) false keeps the window open
+	window.__TAOCKET__.requestClose = async () => {
+		let proceed = true;
+		for (const listener of closeListeners) {
+			try {
+				if ((await listener()) === false) proceed = false;
+			} catch (e) {
+				console.error("beforeclose listener failed:", e);
+			}
+		}
+		post("window", "ResolveClose", proceed);
+	};
+	// Size constraints clear when called without a width
+	const sizeLimit = (event) => (width, height) =>
+		post("window", event, width == null ? null : { width, height });
+
+	window.taocket = {
+		invoke: window.__API__INVOKE,
+		stream,
+		batch,
+		binary,
+		listen,
+		unlisten,
+		emit,
+		// Label of the window this page runs in
+		label: window.__TAOCKET_LABEL__ || "main",
+		windows: {
+			open: builtin("OpenWindow"),
+			close: builtin("CloseWindow"),
+		},
+		window: {
+			minimize: builtin("Minimize"),
+			toggleMaximize: builtin("Maximize"),
+			unmaximize: builtin("UnMaximize"),
+			close: builtin("Close"),
+			focus: builtin("Focus"),
+			startDragging: builtin("Move"),
+			startResizing: builtin("StartResizing"),
+			isMaximized: builtin("IsMaximized"),
+			isMinimized: builtin("IsMinimized"),
+			isFocused: builtin("IsFocus"),
+			getSize: builtin("GetSize"),
+			setSize: (width, height) => post("window", "SetSize", { width, height }),
+			getOuterSize: builtin("GetOuterSize"),
+			setOuterSize: (width, height) => post("window", "SetOuterSize", { width, height }),
+			setMinSize: sizeLimit("SetMinSize"),
+			setMaxSize: sizeLimit("SetMaxSize"),
+			getPosition: builtin("GetPosition"),
+			setPosition: (x, y) => post("window", "SetPosition", { x, y }),
+			center: builtin("Center"),
+			show: () => post("window", "SetVisible", true),
+			hide: () => post("window", "SetVisible", false),
+			isVisible: builtin("IsVisible"),
+			setResizable: builtin("SetResizable"),
+			isResizable: builtin("IsResizable"),
+			getTitle: builtin("GetTitle"),
+			setTitle: builtin("SetTitle"),
+			scaleFactor: builtin("ScaleFactor"),
+			setDecorations: builtin("SetDecorations"),
+			onBeforeClose,
+			// Native resize, move, focus, minimize, theme and scale events
+			onEvent: (callback) => listen("taocket:window", callback),
+		},
+	};
+
+	window.taocket.ready = post("handshake", "Handshake", {
+		version: PROTOCOL_VERSION,
+		url: window.location.href,
+		user_agent: navigator.userAgent,
+	});
+	window.taocket.ready.catch((e) => showProtocolError(e.message));
+
+	window.TaocketError = TaocketError;
 	window.invoke = window.__API__INVOKE;
 })();
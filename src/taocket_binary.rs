@@ -0,0 +1,405 @@
+use futures_util::future::BoxFuture;
+use std::{collections::HashMap, future::Future, sync::Arc};
+use wry::http::{HeaderMap, Method, Request, Response, StatusCode, header};
+
+use crate::{
+    taocket_capability::{self, Access, Capability},
+    taocket_ipc::{IpcError, IpcErrorKind},
+    taocket_limits::Limits,
+    taocket_multiwindow::is_app_origin,
+};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Path prefix of binary commands on the `taocket://` custom protocol
+pub const BINARY_ROUTE: &str = "/__taocket/ipc/";
+
+type BinaryFn = Arc<
+    dyn Fn(BinaryRequest) -> BoxFuture<'static, Result<BinaryResponse, IpcError>> + Send + Sync,
+>;
+
+/// Outcome of a binary request: answered right away (preflight, unknown,
+/// denied) or pending on the command's future
+pub(crate) enum BinaryReply {
+    Ready(Response<Vec<u8>>),
+    Pending(BoxFuture<'static, Response<Vec<u8>>>),
+}
+
+/// Raw request delivered to a binary command
+#[derive(Debug, Clone)]
+pub struct BinaryRequest {
+    pub command: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl BinaryRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header(header::CONTENT_TYPE.as_str())
+    }
+}
+
+/// Bytes returned by a binary command
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryResponse {
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl BinaryResponse {
+    pub fn new(content_type: impl Into<String>, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            content_type: content_type.into(),
+            body: body.into(),
+        }
+    }
+
+    /// `application/octet-stream` response
+    pub fn bytes(body: impl Into<Vec<u8>>) -> Self {
+        Self::new("application/octet-stream", body)
+    }
+}
+
+// ============================================================================
+// Router
+// ============================================================================
+
+/// Binary commands reached with `POST taocket://localhost/__taocket/ipc/<name>`
+#[derive(Default)]
+pub struct BinaryRouter {
+    commands: HashMap<String, BinaryFn>,
+}
+
+impl BinaryRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a command receiving the raw request; it runs on the
+    /// builder's tokio runtime
+    pub fn register<F, Fut, Er>(&mut self, name: impl Into<String>, handler: F)
+    where
+        F: Fn(BinaryRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<BinaryResponse, Er>> + Send + 'static,
+        Er: std::fmt::Display,
+    {
+        let name = name.into();
+        let command: BinaryFn = Arc::new(move |request| {
+            let task = handler(request);
+            Box::pin(async move { task.await.map_err(|e| IpcError::new(e.to_string())) })
+        });
+        if self.commands.insert(name.clone(), command).is_some() {
+            log::warn!(
+                "Binary command '{}' registered twice, keeping the latest",
+                name
+            );
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Answer a request on the binary route; only pages on `app_origin` may
+    /// call, and they must say where they come from
+    pub(crate) fn handle(
+        &self,
+        request: Request<Vec<u8>>,
+        app_origin: &str,
+        capabilities: &[Capability],
+        limits: &Limits,
+    ) -> BinaryReply {
+        let origin = app_origin.to_string();
+        let Some(page) = request_page(request.headers()) else {
+            let error = IpcError::with_kind(
+                IpcErrorKind::PermissionDenied,
+                "Binary request without an Origin or Referer",
+            );
+            log::warn!("Denied binary call: {}", error);
+            return BinaryReply::Ready(error_response(StatusCode::FORBIDDEN, &error, &origin));
+        };
+        if !is_app_origin(&page, app_origin) {
+            let error = IpcError::with_kind(
+                IpcErrorKind::PermissionDenied,
+                format!("Binary request from {} outside the app", page),
+            );
+            log::warn!("Denied binary call: {}", error);
+            return BinaryReply::Ready(error_response(StatusCode::FORBIDDEN, &error, &origin));
+        }
+        // The page may sit on the Windows / Android form of the app origin
+        let origin = taocket_capability::origin_of(&page).to_string();
+        if request.method() == Method::OPTIONS {
+            return BinaryReply::Ready(preflight_response(&origin));
+        }
+
+        let Some(name) = command_name(request.uri().path()) else {
+            return BinaryReply::Ready(error_response(
+                StatusCode::NOT_FOUND,
+                &IpcError::with_kind(IpcErrorKind::UnknownEvent, "Missing binary command name"),
+                &origin,
+            ));
+        };
+        let name = name.to_string();
+
+        let Some(command) = self.commands.get(&name).cloned() else {
+            let error = IpcError::with_kind(
+                IpcErrorKind::UnknownEvent,
+                format!("Unknown binary command '{}'", name),
+            )
+            .event(&name);
+            return BinaryReply::Ready(error_response(StatusCode::NOT_FOUND, &error, &origin));
+        };
+
+        if let Err(error) = taocket_capability::check(capabilities, &page, Access::Command(&name)) {
+            log::warn!("Denied binary call: {}", error);
+            return BinaryReply::Ready(error_response(StatusCode::FORBIDDEN, &error, &origin));
+        }
+
+        if let Err(error) = limits.check_binary(request.body().len()) {
+            log::warn!("Rejected binary call '{}': {}", name, error);
+            return BinaryReply::Ready(error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &error.event(name),
+                &origin,
+            ));
+        }
+
+        let (parts, body) = request.into_parts();
+        let request = BinaryRequest {
+            command: name.clone(),
+            headers: parts.headers,
+            body,
+        };
+        BinaryReply::Pending(Box::pin(async move {
+            match command(request).await {
+                Ok(response) => binary_response(response, &origin),
+                Err(error) => error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &error.event(name),
+                    &origin,
+                ),
+            }
+        }))
+    }
+}
+
+/// Command name of a binary route path, if `path` is on the route
+pub(crate) fn command_name(path: &str) -> Option<&str> {
+    path.strip_prefix(BINARY_ROUTE)
+        .map(|name| name.trim_end_matches('/'))
+        .filter(|name| !name.is_empty())
+}
+
+pub(crate) fn is_binary_route(path: &str) -> bool {
+    path.starts_with(BINARY_ROUTE)
+}
+
+/// Page that made the request, for the origin and capability checks
+fn request_page(headers: &HeaderMap) -> Option<String> {
+    [header::ORIGIN, header::REFERER]
+        .iter()
+        .find_map(|name| headers.get(name)?.to_str().ok())
+        .filter(|page| !page.is_empty())
+        .map(str::to_string)
+}
+
+// ============================================================================
+// Responses
+// ============================================================================
+
+/// CORS headers letting only the app's own pages read responses
+fn cors(builder: wry::http::response::Builder, origin: &str) -> wry::http::response::Builder {
+    builder
+        .header(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            origin.trim_end_matches('/'),
+        )
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, "POST, GET, OPTIONS")
+        .header(header::ACCESS_CONTROL_ALLOW_HEADERS, "*")
+        .header(header::ACCESS_CONTROL_EXPOSE_HEADERS, "Content-Type")
+}
+
+fn binary_response(response: BinaryResponse, origin: &str) -> Response<Vec<u8>> {
+    cors(Response::builder(), origin)
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, response.content_type)
+        .body(response.body)
+        .unwrap_or_else(|e| {
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &IpcError::new(format!("Invalid binary response: {}", e)),
+                origin,
+            )
+        })
+}
+
+/// JSON `IpcError` body, rethrown as a `TaocketError` by `taocket.binary`
+fn error_response(status: StatusCode, error: &IpcError, origin: &str) -> Response<Vec<u8>> {
+    let body = serde_json::to_vec(error).unwrap_or_default();
+    cors(Response::builder(), origin)
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .expect("static error response is valid")
+}
+
+fn preflight_response(origin: &str) -> Response<Vec<u8>> {
+    cors(Response::builder(), origin)
+        .status(StatusCode::NO_CONTENT)
+        .body(Vec::new())
+        .expect("static preflight response is valid")
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APP: &str = "taocket://localhost";
+
+    fn post(path: &str, body: &[u8]) -> Request<Vec<u8>> {
+        Request::builder()
+            .method(Method::POST)
+            .uri(format!("taocket://localhost{}", path))
+            .header(header::CONTENT_TYPE, "image/png")
+            .header(header::ORIGIN, APP)
+            .body(body.to_vec())
+            .unwrap()
+    }
+
+    fn run(reply: BinaryReply) -> Response<Vec<u8>> {
+        match reply {
+            BinaryReply::Ready(response) => response,
+            BinaryReply::Pending(future) => tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(future),
+        }
+    }
+
+    #[test]
+    fn test_command_name() {
+        assert_eq!(command_name("/__taocket/ipc/thumbnail"), Some("thumbnail"));
+        assert_eq!(command_name("/__taocket/ipc/thumbnail/"), Some("thumbnail"));
+        assert_eq!(command_name("/__taocket/ipc/"), None);
+        assert_eq!(command_name("/index.html"), None);
+    }
+
+    #[test]
+    fn test_handle_roundtrip() {
+        let mut router = BinaryRouter::new();
+        router.register("reverse", |request: BinaryRequest| async move {
+            assert_eq!(request.content_type(), Some("image/png"));
+            let mut body = request.body;
+            body.reverse();
+            Ok::<_, String>(BinaryResponse::bytes(body))
+        });
+
+        let response = run(router.handle(
+            post("/__taocket/ipc/reverse", &[1, 2, 3]),
+            APP,
+            &[],
+            &Limits::default(),
+        ));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), &vec![3, 2, 1]);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/octet-stream"
+        );
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], APP);
+    }
+
+    #[test]
+    fn test_handle_errors() {
+        let mut router = BinaryRouter::new();
+        router.register("fail", |_: BinaryRequest| async move {
+            Err::<BinaryResponse, _>("broken")
+        });
+
+        let response = run(router.handle(
+            post("/__taocket/ipc/missing", &[]),
+            APP,
+            &[],
+            &Limits::default(),
+        ));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = run(router.handle(
+            post("/__taocket/ipc/fail", &[]),
+            APP,
+            &[],
+            &Limits::default(),
+        ));
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let error: IpcError = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.message, "broken");
+        assert_eq!(error.event.as_deref(), Some("fail"));
+
+        let limits = Limits::default().max_binary_message_size(2);
+        let response =
+            run(router.handle(post("/__taocket/ipc/fail", &[1, 2, 3]), APP, &[], &limits));
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let error: IpcError = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.kind, IpcErrorKind::LimitExceeded);
+
+        let capabilities = [Capability::new().command("other")];
+        let response = run(router.handle(
+            post("/__taocket/ipc/fail", &[]),
+            APP,
+            &capabilities,
+            &Limits::default(),
+        ));
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_handle_requires_app_origin() {
+        let mut router = BinaryRouter::new();
+        router.register("echo", |request: BinaryRequest| async move {
+            Ok::<_, String>(BinaryResponse::bytes(request.body))
+        });
+        let request = |origin: Option<&str>| {
+            let mut builder = Request::builder()
+                .method(Method::POST)
+                .uri("taocket://localhost/__taocket/ipc/echo");
+            if let Some(origin) = origin {
+                builder = builder.header(header::ORIGIN, origin);
+            }
+            builder.body(vec![1]).unwrap()
+        };
+
+        for origin in [None, Some("https://example.com"), Some("null")] {
+            let response = run(router.handle(request(origin), APP, &[], &Limits::default()));
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let error: IpcError = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(error.kind, IpcErrorKind::PermissionDenied);
+            assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], APP);
+        }
+
+        let response = run(router.handle(
+            request(Some("http://taocket.localhost")),
+            APP,
+            &[],
+            &Limits::default(),
+        ));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://taocket.localhost"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use ts_rs::TS;
+
+use crate::{
+    taocket_channel::ChannelEvent,
+    taocket_hotkey::{HotkeyAndFunc, HotkeyResponse},
+    taocket_ipc::{Handshake, IpcError, IpcErrorKind, IpcResponse, IpcResult, PROTOCOL_VERSION},
+    taocket_multiwindow::WindowOptions,
+    taocket_native_events::NativeWindowEvent,
+    taocket_window::{
+        DISPATCH_EVENT_NAME, IpcKind, IpcMessage, Payload, TxEvent, USER_EVENT_NAME,
+        UserWindowEvent, WindowAttrPayload,
+    },
+};
+
+type Result<T> = std::result::Result<T, BindingsError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BindingsError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("TypeScript export error: {0}")]
+    Export(#[from] ts_rs::ExportError),
+}
+
+/// File name of the generated typed client inside the bindings directory
+pub const CLIENT_FILE: &str = "taocket.ts";
+
+const HEADER: &str = "// This file was generated by taocket. Do not edit this file manually.\n";
+
+/// Export TypeScript definitions for taocket's protocol types, the user's
+/// invoke enum `E` and dispatch enum `X`, plus a typed `invoke`/`listen`
+/// client, into `out_dir`
+pub fn export_bindings<E, X>(out_dir: impl AsRef<Path>) -> Result<PathBuf>
+where
+    E: TS + 'static,
+    X: TS + 'static,
+{
+    let out_dir = out_dir.as_ref();
+    fs::create_dir_all(out_dir)?;
+
+    export_protocol_types(out_dir)?;
+    if E::output_path().is_some() {
+        E::export_all_to(out_dir)?;
+    }
+    if X::output_path().is_some() {
+        X::export_all_to(out_dir)?;
+    }
+
+    let client_path = out_dir.join(CLIENT_FILE);
+    fs::write(&client_path, client_source::<E, X>())?;
+    log::info!("TypeScript bindings written to {:?}", out_dir);
+    Ok(client_path)
+}
+
+fn export_protocol_types(out_dir: &Path) -> Result<()> {
+    Payload::<serde_json::Value>::export_all_to(out_dir)?;
+    IpcMessage::<serde_json::Value>::export_all_to(out_dir)?;
+    ChannelEvent::<serde_json::Value>::export_all_to(out_dir)?;
+    IpcKind::export_all_to(out_dir)?;
+    IpcResponse::export_all_to(out_dir)?;
+    IpcResult::export_all_to(out_dir)?;
+    IpcError::export_all_to(out_dir)?;
+    IpcErrorKind::export_all_to(out_dir)?;
+    Handshake::export_all_to(out_dir)?;
+    UserWindowEvent::export_all_to(out_dir)?;
+    TxEvent::<serde_json::Value>::export_all_to(out_dir)?;
+    HotkeyAndFunc::export_all_to(out_dir)?;
+    HotkeyResponse::export_all_to(out_dir)?;
+    WindowAttrPayload::export_all_to(out_dir)?;
+    WindowOptions::export_all_to(out_dir)?;
+    NativeWindowEvent::export_all_to(out_dir)?;
+    Ok(())
+}
+
+/// Import line and type expression for `T`; types without their own file
+/// (e.g. `()`) are inlined
+fn type_ref<T: TS + 'static>() -> (Option<String>, String) {
+    match T::output_path() {
+        Some(path) => {
+            let module = path.with_extension("");
+            let module = module.to_string_lossy().replace('\\', "/");
+            let name = T::ident();
+            (
+                Some(format!("import type {{ {} }} from \"./{}\";", name, module)),
+                name,
+            )
+        }
+        None => (None, T::inline()),
+    }
+}
+
+fn client_source<E: TS + 'static, X: TS + 'static>() -> String {
+    let (event_import, event_type) = type_ref::<E>();
+    let (dispatch_import, dispatch_type) = type_ref::<X>();
+
+    let mut imports = vec![
+        "import type { IpcError } from \"./IpcError\";".to_string(),
+        "import type { WindowOptions } from \"./WindowOptions\";".to_string(),
+        "import type { NativeWindowEvent } from \"./NativeWindowEvent\";".to_string(),
+    ];
+    imports.extend(event_import);
+    imports.extend(dispatch_import);
+    imports.sort();
+    imports.dedup();
+
+    format!(
+        r#"{HEADER}
+{imports}
+
+export type InvokeEvent = {event_type};
+export type DispatchEvent = {dispatch_type};
+
+export type EventName = InvokeEvent extends {{ type: infer K }} ? K : never;
+export type EventValue<K extends EventName> =
+	Extract<InvokeEvent, {{ type: K }}> extends {{ value: infer V }} ? V : undefined;
+
+export type TaocketError = Error & IpcError;
+
+export interface CallOptions {{
+	/** Reject with a `Timeout` error and cancel the call after this many ms */
+	timeout?: number;
+	/** Reject with a `Cancelled` error and cancel the call when aborted */
+	signal?: AbortSignal;
+}}
+
+export interface InvokeOptions<M = unknown> extends CallOptions {{
+	/** Called for each message the handler sends through its `Channel` */
+	onMessage?: (message: M) => void;
+	/** Called once the handler's channel is closed */
+	onClose?: () => void;
+}}
+
+/** Protocol version this client was generated for */
+export const PROTOCOL_VERSION = {PROTOCOL_VERSION};
+
+export interface BinaryOptions extends CallOptions {{
+	headers?: Record<string, string>;
+	contentType?: string;
+}}
+
+/** One call of a batch: `[event, value]` or `{{ event, value }}` */
+export type BatchCall = [string, unknown?] | {{ event: string; value?: unknown }};
+
+declare global {{
+	interface Window {{
+		__TAOCKET__: {{
+			protocol: number;
+			checkVersion(version: number): boolean;
+		}};
+		taocket: {{
+			/** Settles once the backend accepted the page's protocol version */
+			ready: Promise<{{ version: number }}>;
+			invoke(event: string, value?: unknown, options?: InvokeOptions): Promise<unknown>;
+			stream(event: string, value?: unknown, options?: CallOptions): AsyncIterableIterator<unknown>;
+			batch(calls: BatchCall[], options?: CallOptions): Promise<unknown>[];
+			binary(command: string, body?: BodyInit, options?: BinaryOptions): Promise<Response>;
+			listen(topic: string, callback: (payload: unknown) => void): () => void;
+			unlisten(topic: string, callback: (payload: unknown) => void): void;
+			emit(topic: string, payload?: unknown): Promise<void>;
+			/** Label of the window this page runs in */
+			label: string;
+			windows: {{
+				/** Resolves with the label once the window is open */
+				open(options: WindowOptions): Promise<string>;
+				/** Closing "main" exits the app */
+				close(label: string): Promise<null>;
+			}};
+			window: {{
+				minimize(): Promise<null>;
+				toggleMaximize(): Promise<null>;
+				unmaximize(): Promise<null>;
+				close(): Promise<null>;
+				focus(): Promise<null>;
+				startDragging(): Promise<null>;
+				/** Resize with the mouse from an edge or corner until released */
+				startResizing(edge: "n" | "ne" | "e" | "se" | "s" | "sw" | "w" | "nw"): Promise<null>;
+				isMaximized(): Promise<boolean>;
+				isMinimized(): Promise<boolean>;
+				isFocused(): Promise<boolean>;
+				/** Logical inner size */
+				getSize(): Promise<{{ width: number; height: number }}>;
+				setSize(width: number, height: number): Promise<null>;
+				/** Logical size including the frame */
+				getOuterSize(): Promise<{{ width: number; height: number }}>;
+				setOuterSize(width: number, height: number): Promise<null>;
+				/** Call without arguments to remove the constraint */
+				setMinSize(width?: number, height?: number): Promise<null>;
+				setMaxSize(width?: number, height?: number): Promise<null>;
+				/** Logical outer position */
+				getPosition(): Promise<{{ x: number; y: number }}>;
+				setPosition(x: number, y: number): Promise<null>;
+				/** Center on the current monitor */
+				center(): Promise<null>;
+				show(): Promise<null>;
+				hide(): Promise<null>;
+				isVisible(): Promise<boolean>;
+				setResizable(enabled: boolean): Promise<null>;
+				isResizable(): Promise<boolean>;
+				getTitle(): Promise<string>;
+				setTitle(title: string): Promise<null>;
+				scaleFactor(): Promise<number>;
+				setDecorations(enabled: boolean): Promise<null>;
+				/** Runs before the window closes; return (or resolve to) `false` to keep it open */
+				onBeforeClose(listener: () => boolean | void | Promise<boolean | void>): () => void;
+				/** Native window events; moves and resizes arrive once they settle */
+				onEvent(callback: (event: NativeWindowEvent) => void): () => void;
+			}};
+		}};
+	}}
+}}
+
+window.__TAOCKET__?.checkVersion(PROTOCOL_VERSION);
+
+/** Invoke a user event variant or a named command registered on the builder */
+export function invoke<K extends EventName>(event: K, value?: EventValue<K>, options?: InvokeOptions): Promise<unknown>;
+export function invoke<T = unknown, M = unknown>(command: string, args?: unknown, options?: InvokeOptions<M>): Promise<T>;
+export function invoke(event: string, value?: unknown, options?: InvokeOptions): Promise<unknown> {{
+	return window.taocket.invoke(event, value, options);
+}}
+
+/** Send several invokes in one IPC message; each promise settles on its own */
+export function batch(calls: BatchCall[], options?: CallOptions): Promise<unknown>[] {{
+	return window.taocket.batch(calls, options);
+}}
+
+/** POST raw bytes to a command registered with `TaocketBuilder::binary_command` */
+export function binary(command: string, body?: BodyInit, options?: BinaryOptions): Promise<Response> {{
+	return window.taocket.binary(command, body, options);
+}}
+
+/** Iterate over the messages a command sends through its `Channel` */
+export function stream<M = unknown>(command: string, args?: unknown, options?: CallOptions): AsyncIterableIterator<M> {{
+	return window.taocket.stream(command, args, options) as AsyncIterableIterator<M>;
+}}
+
+function listenTo<T>(name: string, callback: (event: T) => void): () => void {{
+	const handler = (e: Event) => callback((e as CustomEvent<T>).detail);
+	window.addEventListener(name, handler);
+	return () => window.removeEventListener(name, handler);
+}}
+
+/** Listen for a topic emitted from Rust with `emit(topic, payload)` */
+export function listen<T = unknown>(topic: string, callback: (payload: T) => void): () => void {{
+	return window.taocket.listen(topic, callback as (payload: unknown) => void);
+}}
+
+export function unlisten<T = unknown>(topic: string, callback: (payload: T) => void): void {{
+	window.taocket.unlisten(topic, callback as (payload: unknown) => void);
+}}
+
+/** Emit a topic to the listeners registered with `TaocketBuilder::listen` */
+export function emit<T = unknown>(topic: string, payload?: T): Promise<void> {{
+	return window.taocket.emit(topic, payload);
+}}
+
+/** Open a labeled window; resolves with its label */
+export function openWindow(options: WindowOptions): Promise<string> {{
+	return window.taocket.windows.open(options);
+}}
+
+/** Close a window by label; closing "main" exits the app */
+export function closeWindow(label: string): Promise<null> {{
+	return window.taocket.windows.close(label);
+}}
+
+/** Listen for user events sent through the event loop proxy */
+export function listenUser(callback: (event: InvokeEvent) => void): () => void {{
+	return listenTo("{USER_EVENT_NAME}", callback);
+}}
+
+/** Listen for events sent with `Dispatcher::send_user` */
+export function listenDispatch(callback: (event: DispatchEvent) => void): () => void {{
+	return listenTo("{DISPATCH_EVENT_NAME}", callback);
+}}
+"#,
+        imports = imports.join("\n"),
+    )
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use tempfile::TempDir;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, TS)]
+    #[serde(tag = "type", content = "value")]
+    enum FrontEndEvent {
+        Template { name: String },
+        RawFilter,
+    }
+
+    #[test]
+    fn test_client_source_imports_user_types() {
+        let source = client_source::<FrontEndEvent, ()>();
+        assert!(source.contains("import type { FrontEndEvent } from \"./FrontEndEvent\";"));
+        assert!(source.contains("export type InvokeEvent = FrontEndEvent;"));
+        assert!(source.contains("export type DispatchEvent = null;"));
+        assert!(source.contains(USER_EVENT_NAME));
+    }
+
+    #[test]
+    fn test_export_bindings() {
+        let temp_dir = TempDir::new().unwrap();
+        let client = export_bindings::<FrontEndEvent, ()>(temp_dir.path()).unwrap();
+
+        assert!(client.exists());
+        for file in [
+            "FrontEndEvent.ts",
+            "IpcError.ts",
+            "IpcMessage.ts",
+            "ChannelEvent.ts",
+            "TxEvent.ts",
+            "WindowOptions.ts",
+            "NativeWindowEvent.ts",
+        ] {
+            assert!(temp_dir.path().join(file).exists(), "{} missing", file);
+        }
+    }
+}
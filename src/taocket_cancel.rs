@@ -0,0 +1,142 @@
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use tokio::sync::Notify;
+
+// ============================================================================
+// Cancel Token
+// ============================================================================
+
+/// Set when the frontend abandons an invoke call (timeout or `AbortSignal`)
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelInner>,
+}
+
+#[derive(Debug, Default)]
+struct CancelInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        if !self.inner.cancelled.swap(true, Ordering::AcqRel) {
+            self.inner.notify.notify_waiters();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Resolves once the token is cancelled
+    pub async fn cancelled(&self) {
+        let notified = self.inner.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// Tokens of in-flight invoke calls, keyed by call id
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CancelRegistry {
+    tokens: Arc<Mutex<HashMap<i32, CancelToken>>>,
+}
+
+impl CancelRegistry {
+    /// Token for call `id`, created on first use
+    pub fn token(&self, id: i32) -> CancelToken {
+        self.tokens.lock().entry(id).or_default().clone()
+    }
+
+    /// Cancel call `id` if anything is tracking it
+    pub fn cancel(&self, id: i32) {
+        if let Some(token) = self.tokens.lock().remove(&id) {
+            token.cancel();
+        }
+    }
+
+    /// Cancel every tracked call, e.g. when their window closes
+    pub fn cancel_all(&self) {
+        for (_, token) in self.tokens.lock().drain() {
+            token.cancel();
+        }
+    }
+
+    /// Forget call `id` once it has settled
+    pub fn remove(&self, id: i32) {
+        self.tokens.lock().remove(&id);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_registry_shares_tokens() {
+        let registry = CancelRegistry::default();
+        let token = registry.token(1);
+        assert!(!token.is_cancelled());
+
+        registry.cancel(1);
+        assert!(token.is_cancelled());
+        assert!(!registry.token(1).is_cancelled());
+
+        registry.remove(1);
+        registry.cancel(2);
+        assert!(registry.tokens.lock().is_empty());
+
+        let token = registry.token(3);
+        registry.cancel_all();
+        assert!(token.is_cancelled());
+        assert!(registry.tokens.lock().is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_wakes_waiters() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let token = CancelToken::new();
+            let waiter = tokio::spawn({
+                let token = token.clone();
+                async move { token.cancelled().await }
+            });
+            tokio::task::yield_now().await;
+            token.cancel();
+            tokio::time::timeout(Duration::from_secs(1), waiter)
+                .await
+                .expect("waiter not woken")
+                .unwrap();
+
+            // Already cancelled tokens resolve immediately
+            token.cancelled().await;
+        });
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+
+use crate::taocket_ipc::{IpcError, IpcErrorKind};
+
+// ============================================================================
+// Capabilities
+// ============================================================================
+
+/// Wildcard matching every name in a capability list
+pub const ANY: &str = "*";
+
+/// Built-in window commands of the close protocol: `init.js` reports
+/// `beforeclose` listeners and their answer. They are always allowed, so
+/// capabilities never have to list them.
+pub const INTERNAL_WINDOW_COMMANDS: &[&str] = &["GuardClose", "ResolveClose"];
+
+/// Built-in window commands `dragevent.js` sends for drag regions and the
+/// resize border; grant them with `Capability::drag_regions`
+pub const DRAG_REGION_COMMANDS: &[&str] = &["Move", "StartResizing", "Maximize", "Minimize"];
+
+/// Set of IPC calls a page may make. With no capabilities configured every
+/// call is allowed; once one exists, a call must be listed by a capability
+/// whose `origins` match the calling page. `INTERNAL_WINDOW_COMMANDS` are
+/// exempt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Capability {
+    /// Page origins or URL prefixes (ending in `*`) this applies to; empty
+    /// means every page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub origins: Vec<String>,
+    /// Built-in window commands, e.g. `Minimize`, `Close`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub window: Vec<String>,
+    /// Named commands and user event variants
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+    /// Topics JS may emit to Rust listeners
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<String>,
+}
+
+/// What an IPC call is trying to reach
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access<'a> {
+    Window(&'a str),
+    Command(&'a str),
+    Topic(&'a str),
+}
+
+impl Access<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Access::Window(name) | Access::Command(name) | Access::Topic(name) => name,
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Access::Window(_) => "Window command",
+            Access::Command(_) => "Command",
+            Access::Topic(_) => "Topic",
+        }
+    }
+}
+
+impl Capability {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn origin(mut self, origin: impl Into<String>) -> Self {
+        self.origins.push(origin.into());
+        self
+    }
+
+    pub fn window(mut self, name: impl Into<String>) -> Self {
+        self.window.push(name.into());
+        self
+    }
+
+    pub fn command(mut self, name: impl Into<String>) -> Self {
+        self.commands.push(name.into());
+        self
+    }
+
+    pub fn topic(mut self, name: impl Into<String>) -> Self {
+        self.topics.push(name.into());
+        self
+    }
+
+    /// Allow the `DRAG_REGION_COMMANDS`, so drag regions, double-clicks on
+    /// them and the resize border keep working
+    pub fn drag_regions(mut self) -> Self {
+        self.window
+            .extend(DRAG_REGION_COMMANDS.iter().map(|name| name.to_string()));
+        self
+    }
+
+    pub fn applies_to(&self, url: &str) -> bool {
+        self.origins.is_empty() || self.origins.iter().any(|o| origin_matches(o, url))
+    }
+
+    pub fn allows(&self, access: Access<'_>) -> bool {
+        let names = match access {
+            Access::Window(_) => &self.window,
+            Access::Command(_) => &self.commands,
+            Access::Topic(_) => &self.topics,
+        };
+        names.iter().any(|n| n == ANY || n == access.name())
+    }
+}
+
+/// Check `access` from the page at `url` against `capabilities`
+pub fn check(capabilities: &[Capability], url: &str, access: Access<'_>) -> Result<(), IpcError> {
+    if capabilities.is_empty()
+        || matches!(access, Access::Window(name) if INTERNAL_WINDOW_COMMANDS.contains(&name))
+        || capabilities
+            .iter()
+            .any(|c| c.applies_to(url) && c.allows(access))
+    {
+        return Ok(());
+    }
+    Err(IpcError::with_kind(
+        IpcErrorKind::PermissionDenied,
+        format!(
+            "{} '{}' is not allowed from {}",
+            access.describe(),
+            access.name(),
+            origin_of(url)
+        ),
+    )
+    .event(access.name()))
+}
+
+/// `scheme://host[:port]` part of a URL
+pub(crate) fn origin_of(url: &str) -> &str {
+    let Some(scheme_end) = url.find("://") else {
+        return url;
+    };
+    let rest = &url[scheme_end + 3..];
+    match rest.find(['/', '?', '#']) {
+        Some(end) => &url[..scheme_end + 3 + end],
+        None => url,
+    }
+}
+
+fn origin_matches(pattern: &str, url: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => url.starts_with(prefix),
+        None => {
+            let pattern = pattern.trim_end_matches('/');
+            pattern == origin_of(url) || pattern == url.trim_end_matches('/')
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_of() {
+        assert_eq!(origin_of("taocket://localhost/"), "taocket://localhost");
+        assert_eq!(
+            origin_of("http://localhost:5173/index.html?x=1"),
+            "http://localhost:5173"
+        );
+        assert_eq!(origin_of("http://localhost:5173"), "http://localhost:5173");
+    }
+
+    #[test]
+    fn test_no_capabilities_allows_everything() {
+        assert!(check(&[], "http://evil.example", Access::Window("Close")).is_ok());
+    }
+
+    #[test]
+    fn test_internal_commands_are_exempt() {
+        // A page limited to one command still keeps `onBeforeClose` working
+        let capabilities = vec![
+            Capability::new()
+                .origin("taocket://localhost")
+                .command("save"),
+        ];
+        let app = "taocket://localhost/";
+        for name in INTERNAL_WINDOW_COMMANDS {
+            assert!(check(&capabilities, app, Access::Window(name)).is_ok());
+        }
+        assert!(check(&capabilities, app, Access::Window("Close")).is_err());
+        assert!(check(&capabilities, app, Access::Command("GuardClose")).is_err());
+    }
+
+    #[test]
+    fn test_drag_region_commands_need_a_grant() {
+        let app = "taocket://localhost/";
+        let capabilities = vec![Capability::new().command("save")];
+        for name in DRAG_REGION_COMMANDS {
+            let err = check(&capabilities, app, Access::Window(name)).unwrap_err();
+            assert_eq!(err.kind, IpcErrorKind::PermissionDenied);
+        }
+
+        let capabilities = vec![Capability::new().command("save").drag_regions()];
+        for name in DRAG_REGION_COMMANDS {
+            assert!(check(&capabilities, app, Access::Window(name)).is_ok());
+        }
+        assert!(check(&capabilities, app, Access::Window("Close")).is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let capabilities = vec![
+            Capability::new()
+                .origin("taocket://localhost")
+                .window("Minimize")
+                .command(ANY),
+            Capability::new()
+                .origin("http://localhost:5173/settings*")
+                .topic("saved"),
+        ];
+
+        let app = "taocket://localhost/index.html";
+        assert!(check(&capabilities, app, Access::Window("Minimize")).is_ok());
+        assert!(check(&capabilities, app, Access::Command("save")).is_ok());
+
+        let err = check(&capabilities, app, Access::Window("Close")).unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::PermissionDenied);
+        assert_eq!(err.event.as_deref(), Some("Close"));
+        assert!(err.message.contains("taocket://localhost"));
+
+        let settings = "http://localhost:5173/settings/general";
+        assert!(check(&capabilities, settings, Access::Topic("saved")).is_ok());
+        assert!(check(&capabilities, settings, Access::Command("save")).is_err());
+        assert!(
+            check(
+                &capabilities,
+                "http://localhost:5173/",
+                Access::Topic("saved")
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use std::{
+    marker::PhantomData,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use ts_rs::TS;
+
+use crate::{taocket_multiwindow::Target, taocket_window::LoopCommand};
+
+// ============================================================================
+// Message Types
+// ============================================================================
+
+/// Event delivered to the JS side of a channel, keyed by the invoke call id
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[serde(tag = "type", content = "data")]
+pub enum ChannelEvent<T> {
+    /// The handler opened a channel for the call
+    Open,
+    Message(T),
+    /// No more messages will follow
+    Close,
+}
+
+impl<T: Serialize> ChannelEvent<T> {
+    pub(crate) fn to_script(&self, id: i32) -> Result<String, String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        Ok(format!("window.__TAOCKET__.channel({}, {});", id, json))
+    }
+}
+
+// ============================================================================
+// Channel
+// ============================================================================
+
+/// Stream of typed messages from a Rust handler to the frontend caller of a
+/// single invoke call; closed explicitly or once every clone is dropped
+pub struct Channel<T> {
+    inner: Arc<ChannelInner>,
+    _marker: PhantomData<fn(T)>,
+}
+
+struct ChannelInner {
+    id: i32,
+    label: String,
+    loop_tx: Sender<LoopCommand>,
+    closed: AtomicBool,
+}
+
+impl<T: Serialize> Channel<T> {
+    /// Channel for call `id` of window `label`; the `Open` event must
+    /// already be on its way
+    pub(crate) fn new(id: i32, label: String, loop_tx: Sender<LoopCommand>) -> Self {
+        Self {
+            inner: Arc::new(ChannelInner {
+                id,
+                label,
+                loop_tx,
+                closed: AtomicBool::new(false),
+            }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Id of the invoke call this channel belongs to
+    pub fn id(&self) -> i32 {
+        self.inner.id
+    }
+
+    pub fn send(&self, message: T) -> Result<(), String> {
+        if self.is_closed() {
+            return Err(format!("Channel {} is closed", self.inner.id));
+        }
+        self.inner.send(&ChannelEvent::Message(message))
+    }
+
+    /// Tell the frontend no more messages will follow; later sends fail
+    pub fn close(&self) -> Result<(), String> {
+        self.inner.close()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::Acquire)
+    }
+}
+
+impl ChannelInner {
+    fn send<T: Serialize>(&self, event: &ChannelEvent<T>) -> Result<(), String> {
+        let script = event.to_script(self.id)?;
+        self.loop_tx
+            .send(LoopCommand::Script(
+                Target::Window(self.label.clone()),
+                script,
+            ))
+            .map_err(|_| "Event loop is not running".to_string())
+    }
+
+    fn close(&self) -> Result<(), String> {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        self.send(&ChannelEvent::<()>::Close)
+    }
+}
+
+impl Drop for ChannelInner {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+impl<T> Clone for Channel<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Channel<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Channel")
+            .field("id", &self.inner.id)
+            .field("closed", &self.inner.closed.load(Ordering::Acquire))
+            .finish()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(rx: &crossbeam_channel::Receiver<LoopCommand>) -> Vec<String> {
+        rx.try_iter()
+            .map(|command| match command {
+                LoopCommand::Script(Target::Window(label), script) if label == "main" => script,
+                other => panic!("unexpected loop command: {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_channel_event_script() {
+        let script = ChannelEvent::Message(3).to_script(5).unwrap();
+        assert_eq!(
+            script,
+            r#"window.__TAOCKET__.channel(5, {"type":"Message","data":3});"#
+        );
+    }
+
+    #[test]
+    fn test_send_then_close() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let channel = Channel::<u32>::new(1, "main".into(), tx);
+        channel.send(10).unwrap();
+        channel.close().unwrap();
+        assert!(channel.send(11).is_err());
+        channel.close().unwrap();
+
+        let scripts = scripts(&rx);
+        assert_eq!(scripts.len(), 2);
+        assert!(scripts[0].contains(r#""type":"Message","data":10"#));
+        assert!(scripts[1].contains(r#""type":"Close""#));
+    }
+
+    #[test]
+    fn test_close_on_last_drop() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let channel = Channel::<String>::new(2, "main".into(), tx);
+        let clone = channel.clone();
+        drop(channel);
+        assert!(scripts(&rx).is_empty());
+        drop(clone);
+        assert_eq!(scripts(&rx).len(), 1);
+    }
+}
@@ -0,0 +1,209 @@
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{collections::HashMap, future::Future};
+
+use crate::{
+    CustomEvent,
+    taocket_context::{AsyncContext, WindowContext},
+    taocket_ipc::{self, IpcError, IpcErrorKind},
+};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+type CommandFn<E> = Box<dyn Fn(serde_json::Value, WindowContext<E>) -> CommandResult + Send>;
+
+type AsyncCommandFn<E> =
+    Box<dyn Fn(serde_json::Value, AsyncContext<E>) -> BoxFuture<'static, CommandResult> + Send>;
+
+pub type CommandResult = Result<serde_json::Value, IpcError>;
+
+enum Command<E: CustomEvent> {
+    Sync(CommandFn<E>),
+    Async(AsyncCommandFn<E>),
+}
+
+/// Outcome of dispatching a command: sync commands finish immediately,
+/// async ones return a future to spawn on the runtime
+pub(crate) enum Dispatched {
+    Ready(CommandResult),
+    Pending(BoxFuture<'static, CommandResult>),
+}
+
+/// Event shape of a named command call: `{ type: "<name>", value: <args> }`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct CommandCall {
+    #[serde(rename = "type")]
+    pub name: String,
+    #[serde(default)]
+    pub value: serde_json::Value,
+}
+
+// ============================================================================
+// Router
+// ============================================================================
+
+/// Named IPC commands registered on the builder, dispatched by `event.type`
+pub struct CommandRouter<E: CustomEvent = ()> {
+    commands: HashMap<String, Command<E>>,
+}
+
+impl<E: CustomEvent> CommandRouter<E> {
+    pub fn new() -> Self {
+        Self {
+            commands: HashMap::new(),
+        }
+    }
+
+    /// Register a command; arguments are decoded from the invoke value and the
+    /// return value is serialized back to the caller
+    pub fn register<A, R, Er, F>(&mut self, name: impl Into<String>, handler: F)
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        Er: std::fmt::Display,
+        F: Fn(A, WindowContext<E>) -> Result<R, Er> + Send + 'static,
+    {
+        let name = name.into();
+        let command_name = name.clone();
+        let command: CommandFn<E> = Box::new(move |value, ctx| {
+            let args = decode_args::<A>(&command_name, value)?;
+            encode_output(handler(args, ctx))
+        });
+        self.insert(name, Command::Sync(command));
+    }
+
+    /// Register a command whose future runs on the builder's tokio runtime
+    pub fn register_async<A, R, Er, F, Fut>(&mut self, name: impl Into<String>, handler: F)
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        Er: std::fmt::Display,
+        F: Fn(A, AsyncContext<E>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<R, Er>> + Send + 'static,
+    {
+        let name = name.into();
+        let command_name = name.clone();
+        let command: AsyncCommandFn<E> =
+            Box::new(
+                move |value, ctx| match decode_args::<A>(&command_name, value) {
+                    Ok(args) => {
+                        let task = handler(args, ctx);
+                        Box::pin(async move { encode_output(task.await) })
+                    }
+                    Err(e) => Box::pin(async move { Err(e) }),
+                },
+            );
+        self.insert(name, Command::Async(command));
+    }
+
+    fn insert(&mut self, name: String, command: Command<E>) {
+        if self.commands.insert(name.clone(), command).is_some() {
+            log::warn!("Command '{}' registered twice, keeping the latest", name);
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Run the command `name`, or return `None` if it isn't registered
+    pub(crate) fn dispatch(
+        &self,
+        id: i32,
+        call: CommandCall,
+        ctx: WindowContext<E>,
+    ) -> Option<Dispatched> {
+        let dispatched = match self.commands.get(&call.name)? {
+            Command::Sync(command) => Dispatched::Ready(command(call.value, ctx)),
+            Command::Async(command) => match ctx.async_context(id) {
+                Some(async_ctx) => Dispatched::Pending(command(call.value, async_ctx)),
+                None => Dispatched::Ready(Err(IpcError::new("Async runtime not available"))),
+            },
+        };
+        Some(dispatched)
+    }
+}
+
+impl<E: CustomEvent> Default for CommandRouter<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn encode_output<R: Serialize, Er: std::fmt::Display>(output: Result<R, Er>) -> CommandResult {
+    let output = output.map_err(|e| IpcError::new(e.to_string()))?;
+    serde_json::to_value(output)
+        .map_err(|e| IpcError::new(format!("Failed to serialize response: {}", e)))
+}
+
+fn decode_args<A: DeserializeOwned>(name: &str, value: serde_json::Value) -> Result<A, IpcError> {
+    taocket_ipc::decode_value(value).map_err(|e| IpcError {
+        kind: IpcErrorKind::InvalidPayload,
+        message: format!("Invalid arguments for '{}': {}", name, e.message),
+        event: Some(name.to_string()),
+        path: e.path,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct SaveArgs {
+        name: String,
+    }
+
+    #[test]
+    fn test_command_call_without_value() {
+        let call: CommandCall = serde_json::from_str(r#"{"type":"ping"}"#).unwrap();
+        assert_eq!(call.name, "ping");
+        assert_eq!(call.value, serde_json::Value::Null);
+        assert!(decode_args::<()>(&call.name, call.value).is_ok());
+    }
+
+    #[test]
+    fn test_decode_args() {
+        let args: SaveArgs = decode_args("save", json!({"name": "a"})).unwrap();
+        assert_eq!(args.name, "a");
+
+        let err = decode_args::<SaveArgs>("save", json!({"name": 1})).unwrap_err();
+        assert!(err.message.starts_with("Invalid arguments for 'save'"));
+        assert_eq!(err.kind, IpcErrorKind::InvalidPayload);
+        assert_eq!(err.event.as_deref(), Some("save"));
+        assert_eq!(err.path.as_deref(), Some("name"));
+    }
+
+    #[test]
+    fn test_encode_output() {
+        assert_eq!(encode_output(Ok::<_, String>(3)).unwrap(), json!(3));
+        assert_eq!(
+            encode_output(Err::<(), _>("nope")).unwrap_err().message,
+            "nope"
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let mut router = CommandRouter::<()>::new();
+        assert!(router.is_empty());
+        router.register("save", |args: SaveArgs, _ctx| Ok::<_, String>(args.name));
+        router.register_async("load", |args: SaveArgs, _ctx| async move {
+            Ok::<_, String>(args.name)
+        });
+        assert!(router.contains("save"));
+        assert!(router.contains("load"));
+        assert!(!router.contains("delete"));
+    }
+}
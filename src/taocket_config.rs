@@ -6,6 +6,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use crate::{taocket_capability::Capability, taocket_limits::Limits};
+
 type Result<T> = std::result::Result<T, ConfigError>;
 
 #[derive(Debug, thiserror::Error)]
@@ -41,6 +43,177 @@ impl From<(f64, f64)> for WindowSize {
     }
 }
 
+/// Logical screen position
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct WindowPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// What double-clicking a `data-taocket-drag-region` does
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DoubleClickAction {
+    /// Toggle maximize, like a native title bar
+    #[default]
+    Maximize,
+    Minimize,
+    None,
+}
+
+impl DoubleClickAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DoubleClickAction::Maximize => "maximize",
+            DoubleClickAction::Minimize => "minimize",
+            DoubleClickAction::None => "none",
+        }
+    }
+}
+
+/// `[window]` section: attributes of the main window. `transparent` and
+/// `icon` apply to every window
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct WindowConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub decorations: bool,
+    pub transparent: bool,
+    pub resizable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<WindowSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<WindowSize>,
+    /// Initial position; the OS places the window when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<WindowPosition>,
+    /// Show the window on start; hidden windows can be shown with
+    /// `taocket.window.show()`
+    pub visible: bool,
+    /// Leave the window out of the taskbar (Windows and Linux)
+    pub skip_taskbar: bool,
+    pub maximized: bool,
+    /// Window icon image (Windows and Linux)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<PathBuf>,
+    /// Width in CSS pixels of the invisible border frameless windows are
+    /// resized from; `0` leaves only `data-taocket-resize` regions
+    pub resize_border: f64,
+    /// Action of a double-click on a drag region, in every window
+    pub double_click: DoubleClickAction,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            decorations: true,
+            transparent: true,
+            resizable: true,
+            min_size: None,
+            max_size: None,
+            position: None,
+            visible: true,
+            skip_taskbar: false,
+            maximized: false,
+            icon: None,
+            resize_border: 5.0,
+            double_click: DoubleClickAction::Maximize,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn decorations(mut self, enabled: bool) -> Self {
+        self.decorations = enabled;
+        self
+    }
+
+    pub fn transparent(mut self, enabled: bool) -> Self {
+        self.transparent = enabled;
+        self
+    }
+
+    pub fn resizable(mut self, enabled: bool) -> Self {
+        self.resizable = enabled;
+        self
+    }
+
+    pub fn min_size(mut self, width: f64, height: f64) -> Self {
+        self.min_size = Some(WindowSize { width, height });
+        self
+    }
+
+    pub fn max_size(mut self, width: f64, height: f64) -> Self {
+        self.max_size = Some(WindowSize { width, height });
+        self
+    }
+
+    pub fn position(mut self, x: f64, y: f64) -> Self {
+        self.position = Some(WindowPosition { x, y });
+        self
+    }
+
+    pub fn visible(mut self, enabled: bool) -> Self {
+        self.visible = enabled;
+        self
+    }
+
+    pub fn skip_taskbar(mut self, enabled: bool) -> Self {
+        self.skip_taskbar = enabled;
+        self
+    }
+
+    pub fn maximized(mut self, enabled: bool) -> Self {
+        self.maximized = enabled;
+        self
+    }
+
+    pub fn icon(mut self, path: impl Into<PathBuf>) -> Self {
+        self.icon = Some(path.into());
+        self
+    }
+
+    pub fn resize_border(mut self, width: f64) -> Self {
+        self.resize_border = width;
+        self
+    }
+
+    pub fn double_click(mut self, action: DoubleClickAction) -> Self {
+        self.double_click = action;
+        self
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        let positive = |size: &WindowSize| size.width > 0.0 && size.height > 0.0;
+        if self.min_size.as_ref().is_some_and(|size| !positive(size)) {
+            errors.push("window.min_size must be positive".to_string());
+        }
+        if self.max_size.as_ref().is_some_and(|size| !positive(size)) {
+            errors.push("window.max_size must be positive".to_string());
+        }
+        if let (Some(min), Some(max)) = (&self.min_size, &self.max_size)
+            && (min.width > max.width || min.height > max.height)
+        {
+            errors.push("window.min_size cannot exceed window.max_size".to_string());
+        }
+        if self.resize_border < 0.0 {
+            errors.push("window.resize_border cannot be negative".to_string());
+        }
+        if let Some(icon) = &self.icon
+            && !icon.is_file()
+        {
+            errors.push(format!("window.icon {:?} does not exist", icon));
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct TaocketConfig {
     #[serde(skip)]
@@ -54,8 +227,32 @@ pub struct TaocketConfig {
     #[serde(default)]
     pub size: WindowSize,
 
+    /// Directory TypeScript bindings are written to by `export_bindings`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bindings_dir: Option<PathBuf>,
+
     #[serde(default)]
     pub keys: HashMap<String, String>,
+
+    /// IPC allowlist; every call is allowed while this is empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<Capability>,
+
+    /// Message size and nesting limits for IPC and the WebSocket server
+    #[serde(default)]
+    pub limits: Limits,
+
+    /// Save window size and position to the state file and restore them on
+    /// startup; off unless enabled
+    #[serde(default)]
+    pub persist_geometry: bool,
+
+    /// Window state file; defaults to `taocket_state.toml` next to the config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_path: Option<PathBuf>,
+
+    #[serde(default)]
+    pub window: WindowConfig,
 }
 
 impl Default for TaocketConfig {
@@ -68,7 +265,13 @@ impl Default for TaocketConfig {
             devtools: true,
             top_most: false,
             size: WindowSize::default(),
+            bindings_dir: None,
             keys: HashMap::new(),
+            capabilities: Vec::new(),
+            limits: Limits::default(),
+            persist_geometry: false,
+            state_path: None,
+            window: WindowConfig::default(),
         }
     }
 }
@@ -157,6 +360,13 @@ impl TaocketConfig {
         &self.config_path
     }
 
+    /// File window geometry is persisted to
+    pub fn state_path(&self) -> PathBuf {
+        self.state_path
+            .clone()
+            .unwrap_or_else(|| self.config_path.with_file_name("taocket_state.toml"))
+    }
+
     /// Validate configuration
     pub fn validate(&self) -> std::result::Result<(), Vec<String>> {
         let mut errors = Vec::new();
@@ -165,6 +375,15 @@ impl TaocketConfig {
             errors.push("Window size must be positive".to_string());
         }
 
+        self.window.validate(&mut errors);
+
+        if self.limits.max_ipc_message_size == 0
+            || self.limits.max_binary_message_size == 0
+            || self.limits.max_ws_message_size == 0
+        {
+            errors.push("Message size limits must be positive".to_string());
+        }
+
         if self.dev_url.is_empty() {
             errors.push("dev_url cannot be empty".to_string());
         }
@@ -214,11 +433,41 @@ impl TaocketConfigBuilder {
         self
     }
 
+    pub fn bindings_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.bindings_dir = Some(path.into());
+        self
+    }
+
     pub fn hotkey(mut self, key: impl Into<String>, func: impl Into<String>) -> Self {
         self.config.add_hotkey(key, func);
         self
     }
 
+    pub fn capability(mut self, capability: Capability) -> Self {
+        self.config.capabilities.push(capability);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    pub fn window(mut self, window: WindowConfig) -> Self {
+        self.config.window = window;
+        self
+    }
+
+    pub fn persist_geometry(mut self, enabled: bool) -> Self {
+        self.config.persist_geometry = enabled;
+        self
+    }
+
+    pub fn state_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.state_path = Some(path.into());
+        self
+    }
+
     pub fn build(self) -> TaocketConfig {
         self.config
     }
@@ -271,6 +520,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bindings_dir_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("taocket.toml");
+
+        let mut config = TaocketConfig::load(&config_path).unwrap();
+        assert!(config.bindings_dir.is_none());
+        config.bindings_dir = Some(PathBuf::from("./frontend/src/bindings"));
+        config.save().unwrap();
+
+        let loaded = TaocketConfig::load(&config_path).unwrap();
+        assert_eq!(loaded.bindings_dir, config.bindings_dir);
+    }
+
+    #[test]
+    fn test_capabilities_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("taocket.toml");
+
+        let mut config = TaocketConfig::load(&config_path).unwrap();
+        config.add_hotkey("ctrl+s", "save");
+        config.capabilities.push(
+            Capability::new()
+                .origin("taocket://localhost")
+                .window("Minimize")
+                .command("save"),
+        );
+        config.save().unwrap();
+
+        let loaded = TaocketConfig::load(&config_path).unwrap();
+        assert_eq!(loaded.capabilities, config.capabilities);
+    }
+
+    #[test]
+    fn test_window_section() {
+        let config: TaocketConfig = toml::from_str(
+            r#"
+            dev_url = "http://localhost:5173"
+            build_path = "./frontend"
+            websocket_port = 1818
+            devtools = true
+            top_most = false
+
+            [window]
+            title = "Launcher"
+            decorations = false
+            min_size = { width = 200.0, height = 100.0 }
+            double_click = "minimize"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.window,
+            WindowConfig::default()
+                .title("Launcher")
+                .decorations(false)
+                .min_size(200.0, 100.0)
+                .double_click(DoubleClickAction::Minimize)
+        );
+        assert!(config.window.resizable);
+        assert!(config.validate().is_ok());
+
+        let toml = toml::to_string_pretty(&config).unwrap();
+        let loaded: TaocketConfig = toml::from_str(&toml).unwrap();
+        assert_eq!(loaded.window, config.window);
+    }
+
+    #[test]
+    fn test_window_validation() {
+        let config = TaocketConfigBuilder::new()
+            .window(
+                WindowConfig::default()
+                    .min_size(400.0, 300.0)
+                    .max_size(300.0, 600.0),
+            )
+            .build();
+        assert!(config.validate().is_err());
+
+        let config = TaocketConfigBuilder::new()
+            .window(WindowConfig::default().icon("missing/icon.png"))
+            .build();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_state_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("taocket.toml");
+        let config = TaocketConfig::load(&config_path).unwrap();
+        assert!(!config.persist_geometry);
+        assert_eq!(
+            config.state_path(),
+            temp_dir.path().join("taocket_state.toml")
+        );
+
+        let config = TaocketConfigBuilder::new()
+            .state_path("state/window.toml")
+            .build();
+        assert_eq!(config.state_path(), PathBuf::from("state/window.toml"));
+    }
+
     #[test]
     fn test_builder() {
         let config = TaocketConfigBuilder::new()
@@ -293,5 +643,10 @@ mod tests {
 
         config.size.width = -100.0;
         assert!(config.validate().is_err());
+
+        let config = TaocketConfigBuilder::new()
+            .limits(Limits::default().max_ws_message_size(0))
+            .build();
+        assert!(config.validate().is_err());
     }
 }
@@ -1,6 +1,15 @@
-use parking_lot::Mutex;
-use serde::Serialize;
-use std::{collections::HashMap, sync::Arc};
+use crossbeam_channel::Sender;
+use futures_util::future::BoxFuture;
+use parking_lot::{Mutex, MutexGuard};
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 use tao::{
     dpi::{LogicalPosition, LogicalSize},
     event_loop::EventLoopProxy,
@@ -8,6 +17,16 @@ use tao::{
 };
 use wry::WebView;
 
+use crate::{
+    taocket_cancel::{CancelRegistry, CancelToken},
+    taocket_channel::{Channel, ChannelEvent},
+    taocket_event::{self, EventBus, ListenerHandle},
+    taocket_ipc::{self, IpcError, IpcResponse},
+    taocket_middleware::{Extensions, MiddlewareStack, SharedExtensions},
+    taocket_multiwindow::{MAIN_WINDOW, Target, WindowOptions},
+    taocket_window::{LoopCommand, Payload, UserWindowEvent},
+};
+
 // ============================================================================
 // Type Aliases
 // ============================================================================
@@ -15,6 +34,133 @@ use wry::WebView;
 pub type Clients = Arc<Mutex<HashMap<u64, crate::ws::Responder>>>;
 pub type WebviewContext = Arc<Mutex<Option<WebView>>>;
 
+// ============================================================================
+// Loop Handle
+// ============================================================================
+
+/// Runtime handle plus a sender into the event loop, used to run async work
+/// off the UI thread and deliver its results back to window `label`
+#[derive(Debug, Clone)]
+pub(crate) struct LoopHandle {
+    pub label: String,
+    pub loop_tx: Sender<LoopCommand>,
+    pub runtime: tokio::runtime::Handle,
+    pub cancels: CancelRegistry,
+}
+
+impl LoopHandle {
+    pub fn send(&self, command: LoopCommand) -> Result<(), String> {
+        self.loop_tx
+            .send(command)
+            .map_err(|_| "Event loop is not running".to_string())
+    }
+
+    /// Run `task` on the runtime and settle the invoke call `id` with its
+    /// result; the task is aborted if the frontend cancels the call
+    pub fn spawn_response(&self, id: i32, task: PendingTask) {
+        let response = self.run_response(id, task);
+        let label = self.label.clone();
+        let loop_tx = self.loop_tx.clone();
+        self.runtime.spawn(async move {
+            if let Some(response) = response.await {
+                let _ = loop_tx.send(LoopCommand::Respond(label, response));
+            }
+        });
+    }
+
+    /// Run batched `tasks` concurrently and settle them with one combined
+    /// reply once all have finished
+    pub fn spawn_batch(&self, tasks: Vec<(i32, PendingTask)>) {
+        let responses: Vec<_> = tasks
+            .into_iter()
+            .map(|(id, task)| self.run_response(id, task))
+            .collect();
+        let label = self.label.clone();
+        let loop_tx = self.loop_tx.clone();
+        self.runtime.spawn(async move {
+            let responses: Vec<_> = futures_util::future::join_all(responses)
+                .await
+                .into_iter()
+                .flatten()
+                .collect();
+            if !responses.is_empty() {
+                let _ = loop_tx.send(LoopCommand::RespondAll(label, responses));
+            }
+        });
+    }
+
+    /// Spawn `task` and resolve to its response, or `None` if cancelled
+    fn run_response(
+        &self,
+        id: i32,
+        task: PendingTask,
+    ) -> impl Future<Output = Option<IpcResponse>> + Send + 'static {
+        let token = self.cancels.token(id);
+        let cancels = self.cancels.clone();
+        let join = self.runtime.spawn(task);
+        let abort = join.abort_handle();
+        async move {
+            let response = tokio::select! {
+                result = join => match result {
+                    Ok(Ok(value)) => IpcResponse::ok(id, value),
+                    Ok(Err(e)) => IpcResponse::err(id, e),
+                    Err(e) => IpcResponse::err(id, format!("Async task failed: {}", e)),
+                },
+                _ = token.cancelled() => {
+                    abort.abort();
+                    log::debug!("Invoke call {} cancelled by the frontend", id);
+                    return None;
+                }
+            };
+            cancels.remove(id);
+            Some(response)
+        }
+    }
+}
+
+pub(crate) type PendingTask = BoxFuture<'static, Result<serde_json::Value, IpcError>>;
+
+// ============================================================================
+// Reply Batch
+// ============================================================================
+
+/// Collects the replies of a batched IPC message so they can be sent as one
+#[derive(Default)]
+pub(crate) struct ReplyBatch {
+    closed: AtomicBool,
+    responses: Mutex<Vec<IpcResponse>>,
+    deferred: Mutex<Vec<(i32, PendingTask)>>,
+}
+
+impl ReplyBatch {
+    /// Queue `response`, handing it back if the batch was already sent
+    pub fn push(&self, response: IpcResponse) -> Option<IpcResponse> {
+        if self.closed.load(Ordering::Acquire) {
+            return Some(response);
+        }
+        self.responses.lock().push(response);
+        None
+    }
+
+    /// Queue an async command to run with the batch's other async commands
+    pub fn defer(&self, id: i32, task: PendingTask) -> Option<PendingTask> {
+        if self.closed.load(Ordering::Acquire) {
+            return Some(task);
+        }
+        self.deferred.lock().push((id, task));
+        None
+    }
+
+    /// Stop collecting and take what was queued; later replies go out directly
+    pub fn close(&self) -> (Vec<IpcResponse>, Vec<(i32, PendingTask)>) {
+        self.closed.store(true, Ordering::Release);
+        (
+            std::mem::take(&mut *self.responses.lock()),
+            std::mem::take(&mut *self.deferred.lock()),
+        )
+    }
+}
+
 // ============================================================================
 // Script Events
 // ============================================================================
@@ -37,19 +183,31 @@ pub enum ScriptEvent {
 // ============================================================================
 
 pub struct WindowContext<E: Clone + Send + 'static = ()> {
+    label: String,
     window: Arc<Window>,
     webview: WebviewContext,
     clients: Clients,
-    event_proxy: Option<Arc<EventLoopProxy<E>>>,
+    event_proxy: Option<EventLoopProxy<E>>,
+    loop_handle: Option<LoopHandle>,
+    extensions: SharedExtensions,
+    middleware: Option<Arc<MiddlewareStack<E>>>,
+    batch: Option<Arc<ReplyBatch>>,
+    events: Option<EventBus<E>>,
 }
 
 impl<E: Clone + Send + 'static> WindowContext<E> {
     pub fn new(window: Arc<Window>, webview: WebviewContext, clients: Clients) -> Self {
         Self {
+            label: MAIN_WINDOW.to_string(),
             window,
             webview,
             clients,
             event_proxy: None,
+            loop_handle: None,
+            extensions: Default::default(),
+            middleware: None,
+            batch: None,
+            events: None,
         }
     }
 
@@ -58,15 +216,76 @@ impl<E: Clone + Send + 'static> WindowContext<E> {
         webview: WebviewContext,
         clients: Clients,
         proxy: Arc<EventLoopProxy<E>>,
+    ) -> Self {
+        Self::from_proxy(window, webview, clients, Arc::unwrap_or_clone(proxy))
+    }
+
+    /// `with_proxy` without wrapping the proxy in an `Arc`
+    pub(crate) fn from_proxy(
+        window: Arc<Window>,
+        webview: WebviewContext,
+        clients: Clients,
+        proxy: EventLoopProxy<E>,
     ) -> Self {
         Self {
+            label: MAIN_WINDOW.to_string(),
             window,
             webview,
             clients,
             event_proxy: Some(proxy),
+            loop_handle: None,
+            extensions: Default::default(),
+            middleware: None,
+            batch: None,
+            events: None,
         }
     }
 
+    /// Attach the event loop; the context takes the handle's window label
+    pub(crate) fn with_loop_handle(mut self, loop_handle: LoopHandle) -> Self {
+        self.label = loop_handle.label.clone();
+        self.loop_handle = Some(loop_handle);
+        self
+    }
+
+    /// Label of the window this context belongs to
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub(crate) fn with_middleware(mut self, middleware: Arc<MiddlewareStack<E>>) -> Self {
+        self.middleware = Some(middleware);
+        self
+    }
+
+    pub(crate) fn with_events(mut self, events: EventBus<E>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Collect replies into `batch` instead of sending them one by one
+    pub(crate) fn with_batch(mut self, batch: Arc<ReplyBatch>) -> Self {
+        self.batch = Some(batch);
+        self
+    }
+
+    pub(crate) fn batch(&self) -> Option<&Arc<ReplyBatch>> {
+        self.batch.as_ref()
+    }
+
+    // ========================================================================
+    // Extensions
+    // ========================================================================
+
+    /// Values attached to this call by middleware
+    pub fn extensions(&self) -> MutexGuard<'_, Extensions> {
+        self.extensions.lock()
+    }
+
+    pub(crate) fn shared_extensions(&self) -> &SharedExtensions {
+        &self.extensions
+    }
+
     // ========================================================================
     // Script Execution
     // ========================================================================
@@ -101,6 +320,157 @@ impl<E: Clone + Send + 'static> WindowContext<E> {
         webview.evaluate_script(&script).map_err(|e| e.to_string())
     }
 
+    // ========================================================================
+    // Invoke Replies
+    // ========================================================================
+
+    /// Resolve the frontend `invoke` promise with the given id
+    pub fn resolve<T: Serialize>(&self, id: i32, value: T) -> Result<(), String> {
+        self.respond(IpcResponse::from_value(id, value))
+    }
+
+    /// Reject the frontend `invoke` promise with the given id
+    pub fn reject(&self, id: i32, error: impl Into<IpcError>) -> Result<(), String> {
+        self.respond(IpcResponse::err(id, error))
+    }
+
+    /// Resolve or reject depending on `result`
+    pub fn reply<T, Er>(&self, id: i32, result: Result<T, Er>) -> Result<(), String>
+    where
+        T: Serialize,
+        Er: std::fmt::Display,
+    {
+        match result {
+            Ok(value) => self.resolve(id, value),
+            Err(e) => self.reject(id, e.to_string()),
+        }
+    }
+
+    /// Decode `payload.value` into `T`, rejecting the call with the
+    /// path-annotated error when it doesn't fit; `None` means it was rejected
+    pub fn extract<T, P>(&self, payload: &Payload<P>) -> Option<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.accept(payload.id, payload.value_as())
+    }
+
+    /// Like `extract`, but a missing or `null` value is rejected as well
+    pub fn extract_required<T, P>(&self, payload: &Payload<P>) -> Option<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.accept(payload.id, payload.required())
+    }
+
+    /// Unwrap `result`, rejecting call `id` with its error otherwise
+    pub fn accept<T>(&self, id: i32, result: Result<T, IpcError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                log::debug!("Rejecting call #{}: {}", id, error);
+                if let Err(e) = self.reject(id, error) {
+                    log::error!("Failed to reject call: {}", e);
+                }
+                None
+            }
+        }
+    }
+
+    pub fn respond(&self, response: IpcResponse) -> Result<(), String> {
+        if let Some(loop_handle) = &self.loop_handle {
+            loop_handle.cancels.remove(response.id);
+        }
+        if let Some(middleware) = &self.middleware {
+            middleware.finish(&self.label, &response);
+        }
+        let response = match &self.batch {
+            Some(batch) => match batch.push(response) {
+                Some(response) => response,
+                None => return Ok(()),
+            },
+            None => response,
+        };
+        let webview_guard = self.webview.lock();
+        let webview = webview_guard.as_ref().ok_or("Webview not initialized")?;
+        taocket_ipc::send_response(webview, &response)
+    }
+
+    /// Send several replies in one script
+    pub(crate) fn respond_all(&self, responses: &[IpcResponse]) -> Result<(), String> {
+        let webview_guard = self.webview.lock();
+        let webview = webview_guard.as_ref().ok_or("Webview not initialized")?;
+        taocket_ipc::send_responses(webview, responses)
+    }
+
+    // ========================================================================
+    // Channels
+    // ========================================================================
+
+    /// Open a channel streaming messages to the caller of invoke call `id`;
+    /// the channel can be moved to other threads and outlive the handler
+    pub fn channel<T: Serialize>(&self, id: i32) -> Result<Channel<T>, String> {
+        let loop_handle = self
+            .loop_handle
+            .as_ref()
+            .ok_or("Event loop handle not available")?;
+        // Evaluated right away so the frontend sees the channel before a sync reply
+        self.execute_script(ScriptEvent::Raw(ChannelEvent::<()>::Open.to_script(id)?))?;
+        Ok(Channel::new(
+            id,
+            self.label.clone(),
+            loop_handle.loop_tx.clone(),
+        ))
+    }
+
+    // ========================================================================
+    // Async Work
+    // ========================================================================
+
+    /// Token cancelled when the frontend abandons invoke call `id`; only
+    /// cancels that arrive after the token is first requested are seen
+    pub fn cancel_token(&self, id: i32) -> Option<CancelToken> {
+        Some(self.loop_handle.as_ref()?.cancels.token(id))
+    }
+
+    /// Context that can be moved into async work for the invoke call `id`
+    pub fn async_context(&self, id: i32) -> Option<AsyncContext<E>> {
+        let loop_handle = self.loop_handle.clone()?;
+        Some(AsyncContext {
+            id,
+            clients: Arc::clone(&self.clients),
+            event_proxy: self.event_proxy.clone(),
+            cancel: loop_handle.cancels.token(id),
+            extensions: Arc::clone(&self.extensions),
+            loop_handle,
+        })
+    }
+
+    /// Run `task` on the builder's runtime and settle the invoke call `id`
+    /// with its result once it completes
+    pub fn spawn<F, Fut, T, Er>(&self, id: i32, task: F) -> Result<(), String>
+    where
+        F: FnOnce(AsyncContext<E>) -> Fut,
+        Fut: Future<Output = Result<T, Er>> + Send + 'static,
+        T: Serialize,
+        Er: std::fmt::Display,
+    {
+        let ctx = self
+            .async_context(id)
+            .ok_or("Async runtime not available")?;
+        let loop_handle = ctx.loop_handle.clone();
+        let task = task(ctx);
+        loop_handle.spawn_response(
+            id,
+            Box::pin(async move {
+                let value = task.await.map_err(|e| IpcError::new(e.to_string()))?;
+                serde_json::to_value(value)
+                    .map_err(|e| IpcError::new(format!("Failed to serialize response: {}", e)))
+            }),
+        );
+        Ok(())
+    }
+
     // ========================================================================
     // Event Emission
     // ========================================================================
@@ -113,7 +483,76 @@ impl<E: Clone + Send + 'static> WindowContext<E> {
             .as_ref()
             .ok_or("Event proxy not available")?
             .send_event(event)
-            .map_err(|_| format!("Failed to send event"))
+            .map_err(|_| "Failed to send event".to_string())
+    }
+
+    /// Emit `payload` to the JS listeners of `topic`
+    pub fn emit<T: Serialize>(&self, topic: &str, payload: T) -> Result<(), String> {
+        self.execute_script(ScriptEvent::Raw(taocket_event::emit_script(
+            topic, &payload,
+        )?))
+    }
+
+    /// Emit `payload` to the JS listeners of `topic` in the `target` windows
+    pub fn emit_to<T: Serialize>(
+        &self,
+        target: impl Into<Target>,
+        topic: &str,
+        payload: T,
+    ) -> Result<(), String> {
+        let script = taocket_event::emit_script(topic, &payload)?;
+        self.loop_handle()?
+            .send(LoopCommand::Script(target.into(), script))
+    }
+
+    /// Emit `payload` to the JS listeners of `topic` in every window
+    pub fn emit_all<T: Serialize>(&self, topic: &str, payload: T) -> Result<(), String> {
+        self.emit_to(Target::All, topic, payload)
+    }
+
+    /// Listen for a topic emitted from JS, like `TaocketBuilder::listen`
+    /// but while the app runs
+    pub fn listen<T, L>(&self, topic: &str, listener: L) -> Result<ListenerHandle, String>
+    where
+        T: DeserializeOwned,
+        L: Fn(T, WindowContext<E>) + Send + 'static,
+    {
+        Ok(self.event_bus()?.listen(topic, listener))
+    }
+
+    /// Remove a listener added with `listen`; false if it was already removed
+    pub fn unlisten(&self, handle: &ListenerHandle) -> Result<bool, String> {
+        Ok(self.event_bus()?.unlisten(handle))
+    }
+
+    fn event_bus(&self) -> Result<&EventBus<E>, String> {
+        self.events
+            .as_ref()
+            .ok_or_else(|| "Event bus not available".to_string())
+    }
+
+    pub(crate) fn loop_handle(&self) -> Result<&LoopHandle, String> {
+        self.loop_handle
+            .as_ref()
+            .ok_or_else(|| "Event loop handle not available".to_string())
+    }
+
+    // ========================================================================
+    // Windows
+    // ========================================================================
+
+    /// Open a labeled window once control returns to the event loop
+    pub fn open_window(&self, options: WindowOptions) -> Result<(), String> {
+        self.loop_handle()?.send(LoopCommand::OpenWindow {
+            options,
+            reply_to: None,
+        })
+    }
+
+    /// Close window `label`; closing the main window exits the app
+    pub fn close_window(&self, label: impl Into<String>) -> Result<(), String> {
+        self.loop_handle()?
+            .send(LoopCommand::CloseWindow(label.into()))
     }
 
     // ========================================================================
@@ -148,6 +587,14 @@ impl<E: Clone + Send + 'static> WindowContext<E> {
     // Window Operations
     // ========================================================================
 
+    pub fn webview(&self) -> &WebviewContext {
+        &self.webview
+    }
+
+    pub fn event_proxy(&self) -> Option<&EventLoopProxy<E>> {
+        self.event_proxy.as_ref()
+    }
+
     pub fn window(&self) -> &Arc<Window> {
         &self.window
     }
@@ -202,10 +649,151 @@ impl<E: Clone + Send + 'static> WindowContext<E> {
 impl<E: Clone + Send + 'static> Clone for WindowContext<E> {
     fn clone(&self) -> Self {
         Self {
+            label: self.label.clone(),
             window: Arc::clone(&self.window),
             webview: Arc::clone(&self.webview),
             clients: Arc::clone(&self.clients),
             event_proxy: self.event_proxy.clone(),
+            loop_handle: self.loop_handle.clone(),
+            extensions: Arc::clone(&self.extensions),
+            middleware: self.middleware.clone(),
+            batch: self.batch.clone(),
+            events: self.events.clone(),
+        }
+    }
+}
+
+// ============================================================================
+// Async Context
+// ============================================================================
+
+/// `Send` counterpart of `WindowContext` handed to async handlers; window and
+/// webview work is forwarded to the event loop
+pub struct AsyncContext<E: Clone + Send + 'static = ()> {
+    id: i32,
+    clients: Clients,
+    event_proxy: Option<EventLoopProxy<E>>,
+    cancel: CancelToken,
+    extensions: SharedExtensions,
+    loop_handle: LoopHandle,
+}
+
+impl<E: Clone + Send + 'static> AsyncContext<E> {
+    /// Id of the invoke call this context belongs to
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Label of the window that made the call
+    pub fn label(&self) -> &str {
+        &self.loop_handle.label
+    }
+
+    pub fn runtime(&self) -> &tokio::runtime::Handle {
+        &self.loop_handle.runtime
+    }
+
+    /// Token cancelled when the frontend abandons this call
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Values attached to this call by middleware
+    pub fn extensions(&self) -> MutexGuard<'_, Extensions> {
+        self.extensions.lock()
+    }
+
+    pub fn execute_script(&self, script: impl Into<String>) -> Result<(), String> {
+        self.loop_handle.send(LoopCommand::Script(
+            Target::Window(self.loop_handle.label.clone()),
+            script.into(),
+        ))
+    }
+
+    /// Open a channel streaming messages to the caller of this invoke call
+    pub fn channel<T: Serialize>(&self) -> Result<Channel<T>, String> {
+        self.execute_script(ChannelEvent::<()>::Open.to_script(self.id)?)?;
+        Ok(Channel::new(
+            self.id,
+            self.loop_handle.label.clone(),
+            self.loop_handle.loop_tx.clone(),
+        ))
+    }
+
+    pub fn send_window(&self, event: UserWindowEvent) -> Result<(), String> {
+        self.loop_handle
+            .send(LoopCommand::Window(self.loop_handle.label.clone(), event))
+    }
+
+    pub fn open_window(&self, options: WindowOptions) -> Result<(), String> {
+        self.loop_handle.send(LoopCommand::OpenWindow {
+            options,
+            reply_to: None,
+        })
+    }
+
+    pub fn close_window(&self, label: impl Into<String>) -> Result<(), String> {
+        self.loop_handle
+            .send(LoopCommand::CloseWindow(label.into()))
+    }
+
+    pub fn emit_event(&self, event: E) -> Result<(), String>
+    where
+        E: Serialize,
+    {
+        self.event_proxy
+            .as_ref()
+            .ok_or("Event proxy not available")?
+            .send_event(event)
+            .map_err(|_| "Failed to send event".to_string())
+    }
+
+    /// Emit `payload` to the JS listeners of `topic`
+    pub fn emit<T: Serialize>(&self, topic: &str, payload: T) -> Result<(), String> {
+        self.execute_script(taocket_event::emit_script(topic, &payload)?)
+    }
+
+    /// Emit `payload` to the JS listeners of `topic` in the `target` windows
+    pub fn emit_to<T: Serialize>(
+        &self,
+        target: impl Into<Target>,
+        topic: &str,
+        payload: T,
+    ) -> Result<(), String> {
+        let script = taocket_event::emit_script(topic, &payload)?;
+        self.loop_handle
+            .send(LoopCommand::Script(target.into(), script))
+    }
+
+    pub fn emit_all<T: Serialize>(&self, topic: &str, payload: T) -> Result<(), String> {
+        self.emit_to(Target::All, topic, payload)
+    }
+
+    pub fn broadcast(&self, message: impl Into<String>) {
+        let msg = message.into();
+        for (_, client) in self.clients.lock().iter() {
+            let _ = client.send(crate::ws::Message::Text(msg.clone()));
+        }
+    }
+
+    pub fn clients(&self) -> &Clients {
+        &self.clients
+    }
+}
+
+impl<E: Clone + Send + 'static> Clone for AsyncContext<E> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            clients: Arc::clone(&self.clients),
+            event_proxy: self.event_proxy.clone(),
+            cancel: self.cancel.clone(),
+            extensions: Arc::clone(&self.extensions),
+            loop_handle: self.loop_handle.clone(),
         }
     }
 }
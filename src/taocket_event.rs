@@ -0,0 +1,216 @@
+use parking_lot::Mutex;
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    CustomEvent,
+    taocket_context::WindowContext,
+    taocket_ipc::{self, IpcError, IpcErrorKind},
+};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+type ListenerFn<E> =
+    Box<dyn Fn(serde_json::Value, WindowContext<E>) -> Result<(), IpcError> + Send>;
+
+/// Listeners are called outside the bus lock, so they may listen and unlisten
+type SharedListener<E> = Arc<Mutex<ListenerFn<E>>>;
+
+type Listeners<E> = HashMap<String, Vec<(u64, SharedListener<E>)>>;
+
+/// Identifies a listener added with `listen`; pass it to `unlisten`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListenerHandle {
+    topic: String,
+    id: u64,
+}
+
+impl ListenerHandle {
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+}
+
+// ============================================================================
+// Event Bus
+// ============================================================================
+
+/// Rust listeners for topics emitted from JS with `taocket.emit(topic, payload)`;
+/// clones share the same listeners
+pub struct EventBus<E: CustomEvent = ()> {
+    listeners: Arc<Mutex<Listeners<E>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl<E: CustomEvent> EventBus<E> {
+    pub fn new() -> Self {
+        Self {
+            listeners: Default::default(),
+            next_id: Default::default(),
+        }
+    }
+
+    /// Add a listener for `topic`; the payload is decoded into `T`
+    pub fn listen<T, F>(&self, topic: impl Into<String>, listener: F) -> ListenerHandle
+    where
+        T: DeserializeOwned,
+        F: Fn(T, WindowContext<E>) + Send + 'static,
+    {
+        let topic = topic.into();
+        let listener_topic = topic.clone();
+        let listener: ListenerFn<E> = Box::new(move |value, ctx| {
+            let payload = taocket_ipc::decode_value::<T>(value).map_err(|e| IpcError {
+                kind: IpcErrorKind::InvalidPayload,
+                message: format!(
+                    "Invalid payload for topic '{}': {}",
+                    listener_topic, e.message
+                ),
+                event: Some(listener_topic.clone()),
+                path: e.path,
+            })?;
+            listener(payload, ctx);
+            Ok(())
+        });
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.listeners
+            .lock()
+            .entry(topic.clone())
+            .or_default()
+            .push((id, Arc::new(Mutex::new(listener))));
+        ListenerHandle { topic, id }
+    }
+
+    /// Remove the listener behind `handle`; false if it was already removed
+    pub fn unlisten(&self, handle: &ListenerHandle) -> bool {
+        let mut listeners = self.listeners.lock();
+        let Some(topic) = listeners.get_mut(&handle.topic) else {
+            return false;
+        };
+        let before = topic.len();
+        topic.retain(|(id, _)| *id != handle.id);
+        let removed = topic.len() != before;
+        if topic.is_empty() {
+            listeners.remove(&handle.topic);
+        }
+        removed
+    }
+
+    pub fn has_listeners(&self, topic: &str) -> bool {
+        self.listeners
+            .lock()
+            .get(topic)
+            .is_some_and(|l| !l.is_empty())
+    }
+
+    /// Run every listener for `topic`, returning the first decode error
+    pub(crate) fn dispatch(
+        &self,
+        topic: &str,
+        payload: serde_json::Value,
+        ctx: &WindowContext<E>,
+    ) -> Result<(), IpcError> {
+        let listeners: Vec<_> = match self.listeners.lock().get(topic) {
+            Some(listeners) => listeners.iter().map(|(_, l)| Arc::clone(l)).collect(),
+            None => {
+                log::debug!("No Rust listeners for topic '{}'", topic);
+                return Ok(());
+            }
+        };
+        let mut result = Ok(());
+        for listener in listeners {
+            if let Err(e) = (listener.lock())(payload.clone(), ctx.clone())
+                && result.is_ok()
+            {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
+
+impl<E: CustomEvent> Clone for EventBus<E> {
+    fn clone(&self) -> Self {
+        Self {
+            listeners: Arc::clone(&self.listeners),
+            next_id: Arc::clone(&self.next_id),
+        }
+    }
+}
+
+impl<E: CustomEvent> Default for EventBus<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Script delivering `payload` to the JS listeners of `topic`
+pub(crate) fn emit_script<T: Serialize>(topic: &str, payload: &T) -> Result<String, String> {
+    let topic = serde_json::to_string(topic).map_err(|e| e.to_string())?;
+    let payload = serde_json::to_string(payload).map_err(|e| e.to_string())?;
+    Ok(format!("window.__TAOCKET__.emit({}, {});", topic, payload))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[test]
+    fn test_emit_script_escapes_topic() {
+        let script = emit_script("progress\"", &json!({"done": 3})).unwrap();
+        assert_eq!(
+            script,
+            r#"window.__TAOCKET__.emit("progress\"", {"done":3});"#
+        );
+    }
+
+    #[test]
+    fn test_listen() {
+        #[derive(Deserialize)]
+        struct Progress {
+            #[allow(dead_code)]
+            done: u32,
+        }
+
+        let bus = EventBus::<()>::new();
+        assert!(!bus.has_listeners("progress"));
+        bus.listen("progress", |_: Progress, _ctx| {});
+        bus.listen("progress", |_: serde_json::Value, _ctx| {});
+        assert!(bus.has_listeners("progress"));
+        assert_eq!(bus.listeners.lock()["progress"].len(), 2);
+    }
+
+    #[test]
+    fn test_unlisten() {
+        let bus = EventBus::<()>::new();
+        let first = bus.listen("progress", |_: serde_json::Value, _ctx| {});
+        let second = bus.listen("progress", |_: serde_json::Value, _ctx| {});
+        assert_eq!(first.topic(), "progress");
+        assert_ne!(first, second);
+
+        // Clones share listeners, like the bus held by every WindowContext
+        let shared = bus.clone();
+        assert!(shared.unlisten(&first));
+        assert!(!shared.unlisten(&first));
+        assert!(bus.has_listeners("progress"));
+        assert!(bus.unlisten(&second));
+        assert!(!bus.has_listeners("progress"));
+
+        let later = shared.listen("saved", |_: serde_json::Value, _ctx| {});
+        assert!(bus.has_listeners("saved"));
+        assert!(bus.unlisten(&later));
+    }
+}
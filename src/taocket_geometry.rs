@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tao::{
+    dpi::{LogicalSize, PhysicalPosition},
+    monitor::MonitorHandle,
+    window::{Window, WindowBuilder},
+};
+
+use crate::taocket_config::ConfigError;
+
+// ============================================================================
+// Geometry
+// ============================================================================
+
+/// Part of the title bar that must stay on a monitor for a saved position to
+/// be restored, in physical pixels
+const MIN_VISIBLE_WIDTH: i32 = 64;
+const MIN_VISIBLE_HEIGHT: i32 = 16;
+
+/// How long geometry changes settle before the state file is written
+pub const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Last known geometry of a window; the size is logical and the position is
+/// the physical outer position, so it compares against monitor bounds
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WindowGeometry {
+    pub width: f64,
+    pub height: f64,
+    pub x: i32,
+    pub y: i32,
+    #[serde(default)]
+    pub maximized: bool,
+    /// Name of the monitor the window was on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<String>,
+}
+
+impl WindowGeometry {
+    /// Read the geometry of `window`; a maximized window keeps the bounds of
+    /// `previous` so it un-maximizes to them after a restart. Minimized
+    /// windows are skipped
+    pub fn capture(window: &Window, previous: Option<&WindowGeometry>) -> Option<Self> {
+        if window.is_minimized() {
+            return None;
+        }
+        let monitor = window.current_monitor().and_then(|m| m.name());
+        if window.is_maximized()
+            && let Some(previous) = previous
+        {
+            return Some(Self {
+                maximized: true,
+                monitor,
+                ..previous.clone()
+            });
+        }
+        let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+        let position = window.outer_position().ok()?;
+        Some(Self {
+            width: size.width,
+            height: size.height,
+            x: position.x,
+            y: position.y,
+            maximized: window.is_maximized(),
+            monitor,
+        })
+    }
+
+    /// Whether enough of the title bar lies on one of `monitors` to grab it
+    pub fn is_visible_on(&self, monitors: &[MonitorRect]) -> bool {
+        let needed_width = MIN_VISIBLE_WIDTH.min(self.width.max(1.0) as i32);
+        monitors.iter().any(|monitor| {
+            let left = self.x.max(monitor.x);
+            let right = (self.x + self.width as i32).min(monitor.x + monitor.width as i32);
+            let top = self.y.max(monitor.y);
+            let bottom = (self.y + MIN_VISIBLE_HEIGHT).min(monitor.y + monitor.height as i32);
+            right - left >= needed_width && bottom - top >= MIN_VISIBLE_HEIGHT
+        })
+    }
+
+    /// Apply the saved size and maximized state, and the position if it is
+    /// still on a connected monitor
+    pub(crate) fn apply(&self, builder: WindowBuilder, monitors: &[MonitorRect]) -> WindowBuilder {
+        let mut builder = builder.with_maximized(self.maximized);
+        if self.width > 0.0 && self.height > 0.0 {
+            builder = builder.with_inner_size(LogicalSize::new(self.width, self.height));
+        }
+        if self.is_visible_on(monitors) {
+            builder = builder.with_position(PhysicalPosition::new(self.x, self.y));
+        } else {
+            log::info!(
+                "Saved window position ({}, {}) is off-screen, letting the OS place it",
+                self.x,
+                self.y
+            );
+        }
+        builder
+    }
+}
+
+/// Physical bounds of a connected monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl From<&MonitorHandle> for MonitorRect {
+    fn from(monitor: &MonitorHandle) -> Self {
+        let position = monitor.position();
+        let size = monitor.size();
+        Self {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+// ============================================================================
+// State File
+// ============================================================================
+
+/// Contents of the window state file, keyed by window label
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GeometryState {
+    #[serde(default)]
+    pub windows: BTreeMap<String, WindowGeometry>,
+}
+
+impl GeometryState {
+    /// Read the state file; a missing or unreadable file starts empty
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+        match fs::read_to_string(path)
+            .map_err(ConfigError::from)
+            .and_then(|content| Ok(toml::from_str(&content)?))
+        {
+            Ok(state) => state,
+            Err(e) => {
+                log::warn!("Ignoring window state file {:?}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Saved geometry plus the debounce timer, owned by the event loop
+pub(crate) struct GeometryTracker {
+    path: PathBuf,
+    state: GeometryState,
+    dirty_since: Option<Instant>,
+}
+
+impl GeometryTracker {
+    pub fn load(path: PathBuf) -> Self {
+        Self {
+            state: GeometryState::load(&path),
+            path,
+            dirty_since: None,
+        }
+    }
+
+    pub fn saved(&self, label: &str) -> Option<&WindowGeometry> {
+        self.state.windows.get(label)
+    }
+
+    /// Capture `window` and schedule a save if its geometry changed
+    pub fn track(&mut self, label: &str, window: &Window) {
+        if let Some(geometry) = WindowGeometry::capture(window, self.saved(label)) {
+            self.record(label, geometry, Instant::now());
+        }
+    }
+
+    fn record(&mut self, label: &str, geometry: WindowGeometry, now: Instant) {
+        if self.saved(label) == Some(&geometry) {
+            return;
+        }
+        self.state.windows.insert(label.to_string(), geometry);
+        self.dirty_since = Some(now);
+    }
+
+    /// Save once changes have settled for `SAVE_DEBOUNCE`
+    pub fn flush_due(&mut self, now: Instant) {
+        if self
+            .dirty_since
+            .is_some_and(|since| now.duration_since(since) >= SAVE_DEBOUNCE)
+        {
+            self.flush();
+        }
+    }
+
+    /// Save pending changes right away
+    pub fn flush(&mut self) {
+        if self.dirty_since.take().is_some()
+            && let Err(e) = self.state.save(&self.path)
+        {
+            log::error!("Failed to save window state to {:?}: {}", self.path, e);
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn geometry(x: i32, y: i32) -> WindowGeometry {
+        WindowGeometry {
+            width: 800.0,
+            height: 600.0,
+            x,
+            y,
+            maximized: false,
+            monitor: Some("DP-1".into()),
+        }
+    }
+
+    #[test]
+    fn test_is_visible_on() {
+        let monitors = [
+            MonitorRect {
+                x: 0,
+                y: 0,
+                width: 1920,
+                height: 1080,
+            },
+            MonitorRect {
+                x: 1920,
+                y: 0,
+                width: 1280,
+                height: 1024,
+            },
+        ];
+        assert!(geometry(100, 100).is_visible_on(&monitors));
+        assert!(geometry(2500, 500).is_visible_on(&monitors));
+        // Only the left 20px of the title bar are on screen
+        assert!(!geometry(-780, 100).is_visible_on(&monitors));
+        // Below the shorter second monitor
+        assert!(!geometry(2500, 1050).is_visible_on(&monitors));
+        assert!(!geometry(100, 100).is_visible_on(&[]));
+    }
+
+    #[test]
+    fn test_tracker_debounce_and_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("state.toml");
+        let mut tracker = GeometryTracker::load(path.clone());
+        assert!(tracker.saved("main").is_none());
+
+        let start = Instant::now();
+        tracker.record("main", geometry(10, 20), start);
+        tracker.flush_due(start + SAVE_DEBOUNCE / 2);
+        assert!(!path.exists());
+
+        tracker.flush_due(start + SAVE_DEBOUNCE);
+        let loaded = GeometryState::load(&path);
+        assert_eq!(loaded.windows["main"], geometry(10, 20));
+
+        let reloaded = GeometryTracker::load(path);
+        assert_eq!(reloaded.saved("main"), Some(&geometry(10, 20)));
+    }
+
+    #[test]
+    fn test_load_invalid_state_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("state.toml");
+        fs::write(&path, "not = [valid").unwrap();
+        assert_eq!(GeometryState::load(&path), GeometryState::default());
+    }
+}
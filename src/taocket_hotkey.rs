@@ -4,7 +4,8 @@ use global_hotkey::{
 };
 
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display, str::FromStr};
+use std::{collections::HashMap, fmt::Display};
+use ts_rs::TS;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HotkeyInfo {
@@ -13,14 +14,14 @@ pub struct HotkeyInfo {
     pub hotkey_id: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(tag = "type", content = "data")]
 pub enum HotkeyResponse {
     Registered { key: String },
     Failed { err: String },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
 pub struct HotkeyAndFunc {
     pub key: String,
     pub func: String,
@@ -57,7 +58,7 @@ impl TaocketHotkeyManager {
                     }
                 }
                 Err(err) => {
-                    println!("Error parsing key {}: {:?}", k, err);
+                    log::warn!("Error parsing key {}: {:?}", k, err);
                 }
             }
         }
@@ -68,24 +69,22 @@ impl TaocketHotkeyManager {
 
     /// Register a hotkey with a client-provided ID
     pub fn register_hotkey(&mut self, key: HotkeyAndFunc) -> HotkeyResponse {
-        match parse_hotkey(&key.key.to_string()) {
+        match parse_hotkey(&key.key) {
             Ok(ok_key) => match self.manager.register(ok_key) {
                 Ok(_) => {
                     self.registered_hotkeys.insert(ok_key.id(), key.clone());
-                    return HotkeyResponse::Registered {
+                    HotkeyResponse::Registered {
                         key: key.to_string(),
-                    };
+                    }
                 }
                 Err(err) => {
                     let err: String = format!("Error:{err:?}");
-                    return HotkeyResponse::Failed { err: err };
+                    HotkeyResponse::Failed { err }
                 }
             },
-            Err(err) => {
-                return HotkeyResponse::Failed {
-                    err: format!("{err:?}"),
-                };
-            }
+            Err(err) => HotkeyResponse::Failed {
+                err: format!("{err:?}"),
+            },
         }
     }
 }
@@ -0,0 +1,463 @@
+use serde::{
+    Deserialize, Serialize,
+    de::{DeserializeOwned, value::MapDeserializer},
+};
+use ts_rs::TS;
+use wry::WebView;
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, TS)]
+pub enum IpcErrorKind {
+    /// The handler itself returned an error
+    #[default]
+    Handler,
+    /// The message was not valid JSON or didn't match the IPC envelope
+    InvalidMessage,
+    /// No command or event variant matches the event name
+    UnknownEvent,
+    /// The event name is known but its value failed to decode
+    InvalidPayload,
+    /// The calling page's capabilities don't allow the call
+    PermissionDenied,
+    /// The frontend stopped waiting for the call (JS side only)
+    Timeout,
+    /// The frontend aborted the call
+    Cancelled,
+    /// The page speaks a different protocol version than the backend
+    ProtocolMismatch,
+    /// The message is larger or more deeply nested than the configured limits
+    LimitExceeded,
+}
+
+/// Error sent back to the frontend when an invoke call fails
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+pub struct IpcError {
+    #[serde(default)]
+    pub kind: IpcErrorKind,
+    pub message: String,
+    /// Event or command name the call was made with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    /// Path to the offending field, e.g. `payload.event.value.name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl IpcError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self::with_kind(IpcErrorKind::Handler, message)
+    }
+
+    pub fn with_kind(kind: IpcErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            event: None,
+            path: None,
+        }
+    }
+
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Build a decode error from a serde error, keeping the field path
+    pub(crate) fn from_decode(error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = error.path().to_string();
+        let inner = error.into_inner();
+        let message = inner.to_string();
+        let kind = if inner.is_syntax() || inner.is_eof() {
+            IpcErrorKind::InvalidMessage
+        } else {
+            IpcErrorKind::InvalidPayload
+        };
+        let error = Self::with_kind(kind, message);
+        if path == "." { error } else { error.path(path) }
+    }
+}
+
+impl std::fmt::Display for IpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.event, &self.path) {
+            (Some(event), Some(path)) => write!(f, "{} ({} at {})", self.message, event, path),
+            (Some(event), None) => write!(f, "{} ({})", self.message, event),
+            (None, Some(path)) => write!(f, "{} (at {})", self.message, path),
+            (None, None) => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for IpcError {}
+
+impl From<String> for IpcError {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<&str> for IpcError {
+    fn from(message: &str) -> Self {
+        Self::new(message)
+    }
+}
+
+// ============================================================================
+// Handshake
+// ============================================================================
+
+/// Version of the JSON shapes exchanged with `init.js`; bump whenever
+/// `IpcMessage`, `Payload`, `IpcResponse` or the built-in commands change
+/// incompatibly:
+///
+/// - 2: `batch` messages
+/// - 3: built-ins carry their data under `value`; size and position built-ins
+/// - 4: `OpenWindow` / `CloseWindow` built-ins
+/// - 5: window geometry built-ins
+/// - 6: `GuardClose` / `ResolveClose` built-ins for the close path
+/// - 7: `SetVisible` / `IsVisible` built-ins
+/// - 8: `StartResizing` built-in for frameless windows
+pub const PROTOCOL_VERSION: u32 = 8;
+
+/// Announced by `init.js` on every page load
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+pub struct Handshake {
+    /// Protocol version the page speaks
+    pub version: u32,
+    /// URL of the page that loaded
+    #[serde(default)]
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub user_agent: Option<String>,
+}
+
+impl Handshake {
+    /// Read a handshake from a raw message, tolerating envelope changes
+    /// between versions so mismatches can still be reported
+    pub(crate) fn from_body(body: &str) -> Option<Self> {
+        let message = serde_json::from_str::<serde_json::Value>(body).ok()?;
+        let value = message.pointer("/payload/event/value")?;
+        serde_json::from_value(value.clone()).ok()
+    }
+
+    pub fn is_compatible(&self) -> bool {
+        self.version == PROTOCOL_VERSION
+    }
+
+    /// Reply to handshake call `id`: the backend version, or a mismatch
+    /// error for pages speaking another version
+    pub(crate) fn reply(&self, id: i32) -> IpcResponse {
+        if self.is_compatible() {
+            IpcResponse::ok(id, serde_json::json!({ "version": PROTOCOL_VERSION }))
+        } else {
+            IpcResponse::err(id, self.mismatch_error())
+        }
+    }
+
+    pub(crate) fn mismatch_error(&self) -> IpcError {
+        IpcError::with_kind(
+            IpcErrorKind::ProtocolMismatch,
+            format!(
+                "Frontend speaks taocket protocol v{} but the backend expects v{}; reload or rebuild the frontend",
+                self.version, PROTOCOL_VERSION
+            ),
+        )
+    }
+}
+
+// ============================================================================
+// Responses
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[serde(tag = "status", content = "data")]
+pub enum IpcResult {
+    Ok(serde_json::Value),
+    Err(IpcError),
+}
+
+/// Settles the pending `invoke` promise with the same id on the JS side
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+pub struct IpcResponse {
+    pub id: i32,
+    #[serde(flatten)]
+    pub result: IpcResult,
+}
+
+impl IpcResponse {
+    pub fn ok(id: i32, value: serde_json::Value) -> Self {
+        Self {
+            id,
+            result: IpcResult::Ok(value),
+        }
+    }
+
+    pub fn err(id: i32, error: impl Into<IpcError>) -> Self {
+        Self {
+            id,
+            result: IpcResult::Err(error.into()),
+        }
+    }
+
+    /// Build a response from any serializable value, rejecting if serialization fails
+    pub fn from_value<T: Serialize>(id: i32, value: T) -> Self {
+        match serde_json::to_value(value) {
+            Ok(value) => Self::ok(id, value),
+            Err(e) => Self::err(id, format!("Failed to serialize response: {}", e)),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self.result, IpcResult::Ok(_))
+    }
+
+    pub(crate) fn to_script(&self) -> Result<String, String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        Ok(format!("window.__TAOCKET__.settle({});", json))
+    }
+}
+
+/// Decode a JSON string, reporting the path of the first field that failed
+pub fn decode_str<T: DeserializeOwned>(json: &str) -> Result<T, IpcError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(json);
+    serde_path_to_error::deserialize(deserializer).map_err(IpcError::from_decode)
+}
+
+/// Decode a JSON value, reporting the path of the first field that failed
+pub fn decode_value<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, IpcError> {
+    serde_path_to_error::deserialize(value).map_err(IpcError::from_decode)
+}
+
+/// Reject `event` with `UnknownEvent` unless `T` has a variant tagged with
+/// it, like `{ "type": event, ... }`
+pub fn check_event<T: DeserializeOwned>(event: &str) -> Result<(), IpcError> {
+    // serde reports unknown tags through `Error::unknown_variant`, so probe
+    // `T` with just the tag and an error type that records that call
+    let tag = [("type", event)].into_iter();
+    let probe = MapDeserializer::<_, TagProbe>::new(tag);
+    match T::deserialize(probe) {
+        Err(TagProbe::UnknownVariant) => Err(IpcError::with_kind(
+            IpcErrorKind::UnknownEvent,
+            format!("Unknown event '{}'", event),
+        )
+        .event(event)),
+        _ => Ok(()),
+    }
+}
+
+/// Error of `check_event`'s probe; only an unknown tag matters
+#[derive(Debug)]
+enum TagProbe {
+    UnknownVariant,
+    Other,
+}
+
+impl std::fmt::Display for TagProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagProbe::UnknownVariant => write!(f, "unknown variant"),
+            TagProbe::Other => write!(f, "probe failed"),
+        }
+    }
+}
+
+impl std::error::Error for TagProbe {}
+
+impl serde::de::Error for TagProbe {
+    fn custom<T: std::fmt::Display>(_msg: T) -> Self {
+        TagProbe::Other
+    }
+
+    fn unknown_variant(_variant: &str, _expected: &'static [&'static str]) -> Self {
+        TagProbe::UnknownVariant
+    }
+}
+
+/// Evaluate the settle script for `response` in the given webview
+pub(crate) fn send_response(webview: &WebView, response: &IpcResponse) -> Result<(), String> {
+    let script = response.to_script()?;
+    webview.evaluate_script(&script).map_err(|e| e.to_string())
+}
+
+/// Script settling every response of a batch in one evaluation
+pub(crate) fn batch_script(responses: &[IpcResponse]) -> Result<String, String> {
+    let json = serde_json::to_string(responses).map_err(|e| e.to_string())?;
+    Ok(format!("window.__TAOCKET__.settleAll({});", json))
+}
+
+/// Evaluate the settle script for several responses in the given webview
+pub(crate) fn send_responses(webview: &WebView, responses: &[IpcResponse]) -> Result<(), String> {
+    let script = batch_script(responses)?;
+    webview.evaluate_script(&script).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_ok_response_shape() {
+        let response = IpcResponse::ok(7, json!({"maximized": true}));
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            json!({"id": 7, "status": "Ok", "data": {"maximized": true}})
+        );
+    }
+
+    #[test]
+    fn test_err_response_roundtrip() {
+        let response = IpcResponse::err(3, "boom");
+        let json = serde_json::to_string(&response).unwrap();
+        let deserialized: IpcResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(response, deserialized);
+        assert!(!deserialized.is_ok());
+    }
+
+    #[test]
+    fn test_decode_reports_path() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Args {
+            name: String,
+            count: u32,
+        }
+
+        let err = decode_value::<Args>(json!({"name": "a", "count": "x"})).unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::InvalidPayload);
+        assert_eq!(err.path.as_deref(), Some("count"));
+
+        let err = decode_str::<Args>("{not json").unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::InvalidMessage);
+    }
+
+    #[test]
+    fn test_decode_unknown_variant() {
+        #[derive(Debug, Deserialize)]
+        #[serde(tag = "type", content = "value")]
+        enum Event {
+            Save,
+        }
+
+        let err = check_event::<Event>("Sav").unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::UnknownEvent);
+        assert_eq!(err.event.as_deref(), Some("Sav"));
+        assert!(check_event::<Event>("Save").is_ok());
+        assert!(matches!(
+            decode_value::<Event>(json!({"type": "Save"})),
+            Ok(Event::Save)
+        ));
+    }
+
+    #[test]
+    fn test_check_event_variants_with_values() {
+        #[derive(Debug, Deserialize)]
+        #[serde(tag = "type", content = "value")]
+        #[allow(dead_code)]
+        enum Event {
+            Rename { name: String },
+            Resize(f64, f64),
+        }
+
+        assert!(check_event::<Event>("Rename").is_ok());
+        assert!(check_event::<Event>("Resize").is_ok());
+        let err = check_event::<Event>("Move").unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::UnknownEvent);
+
+        // A known tag with a bad value is a payload error, not an unknown event
+        let err = decode_value::<Event>(json!({"type": "Rename", "value": 3})).unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::InvalidPayload);
+    }
+
+    #[test]
+    fn test_error_serialization_skips_empty_fields() {
+        let json = serde_json::to_value(IpcError::new("boom")).unwrap();
+        assert_eq!(json, json!({"kind": "Handler", "message": "boom"}));
+
+        let json = serde_json::to_value(IpcError::new("boom").event("save").path("name")).unwrap();
+        assert_eq!(json["event"], "save");
+        assert_eq!(json["path"], "name");
+    }
+
+    #[test]
+    fn test_batch_script() {
+        let script =
+            batch_script(&[IpcResponse::ok(1, json!(1)), IpcResponse::err(2, "boom")]).unwrap();
+        assert_eq!(
+            script,
+            r#"window.__TAOCKET__.settleAll([{"id":1,"status":"Ok","data":1},{"id":2,"status":"Err","data":{"kind":"Handler","message":"boom"}}]);"#
+        );
+    }
+
+    #[test]
+    fn test_handshake_from_body() {
+        let body = format!(
+            r#"{{"kind":"handshake","payload":{{"id":1,"event":{{"type":"Handshake","value":{{"version":{},"url":"taocket://localhost/"}}}}}}}}"#,
+            PROTOCOL_VERSION
+        );
+        let handshake = Handshake::from_body(&body).unwrap();
+        assert!(handshake.is_compatible());
+        assert_eq!(handshake.url, "taocket://localhost/");
+
+        let stale = Handshake {
+            version: PROTOCOL_VERSION + 1,
+            ..handshake
+        };
+        assert!(!stale.is_compatible());
+        assert_eq!(stale.mismatch_error().kind, IpcErrorKind::ProtocolMismatch);
+        assert!(Handshake::from_body(r#"{"payload":{"id":1}}"#).is_none());
+    }
+
+    #[test]
+    fn test_handshake_reply() {
+        let body = |version: u32| {
+            format!(
+                r#"{{"kind":"handshake","payload":{{"id":7,"event":{{"type":"Handshake","value":{{"version":{},"url":"taocket://localhost/"}}}}}}}}"#,
+                version
+            )
+        };
+
+        let reply = Handshake::from_body(&body(PROTOCOL_VERSION))
+            .unwrap()
+            .reply(7);
+        assert_eq!(reply.id, 7);
+        assert_eq!(
+            reply.result,
+            IpcResult::Ok(json!({ "version": PROTOCOL_VERSION }))
+        );
+
+        // A page built against the first protocol
+        let reply = Handshake::from_body(&body(1)).unwrap().reply(7);
+        assert_eq!(reply.id, 7);
+        match reply.result {
+            IpcResult::Err(error) => {
+                assert_eq!(error.kind, IpcErrorKind::ProtocolMismatch);
+                assert!(error.message.contains("v1"));
+            }
+            IpcResult::Ok(value) => panic!("stale page accepted: {}", value),
+        }
+    }
+
+    #[test]
+    fn test_settle_script() {
+        let script = IpcResponse::ok(1, json!(null)).to_script().unwrap();
+        assert_eq!(
+            script,
+            r#"window.__TAOCKET__.settle({"id":1,"status":"Ok","data":null});"#
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Adapter for apps written against the old `AppBuilder` / `AppConfig` API.
+//!
+//! Everything here runs on `TaocketBuilder`; handlers receive a
+//! `HandlerContext` with the old public fields that derefs to the
+//! `WindowContext` it wraps. Reply with `emit!(&ctx.webview, ..)` /
+//! `emit_err!` as before, or with `ctx.resolve` / `ctx.reject` and their
+//! `resolve!` / `reject!` macros. Window commands from old frontends keep
+//! reporting sizes and positions in physical pixels.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{ops::Deref, path::PathBuf, sync::Arc};
+use tao::{event_loop::EventLoopProxy, window::Window};
+
+use crate::{
+    CustomEvent,
+    taocket_config::TaocketConfigBuilder,
+    taocket_context::{WebviewContext, WindowContext},
+    taocket_ipc::{self, IpcError, IpcResponse},
+    taocket_window::{NoAssets, TaocketBuilder},
+};
+
+pub use crate::CustomEvent as UserEvent;
+pub use crate::taocket_window::Payload;
+
+// ============================================================================
+// Handler Context
+// ============================================================================
+
+/// Context passed to legacy handlers: the old public fields, plus every
+/// `WindowContext` method through `Deref`
+pub struct HandlerContext<E: CustomEvent> {
+    pub webview: WebviewContext,
+    pub window: Arc<Window>,
+    pub event_proxy: EventLoopProxy<E>,
+    context: WindowContext<E>,
+}
+
+impl<E: CustomEvent> HandlerContext<E> {
+    fn new(context: WindowContext<E>, event_proxy: EventLoopProxy<E>) -> Self {
+        Self {
+            webview: Arc::clone(context.webview()),
+            window: Arc::clone(context.window()),
+            event_proxy,
+            context,
+        }
+    }
+
+    /// The `WindowContext` this context wraps
+    pub fn context(&self) -> &WindowContext<E> {
+        &self.context
+    }
+}
+
+impl<E: CustomEvent> Deref for HandlerContext<E> {
+    type Target = WindowContext<E>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.context
+    }
+}
+
+impl<E: CustomEvent> Clone for HandlerContext<E> {
+    fn clone(&self) -> Self {
+        Self {
+            webview: Arc::clone(&self.webview),
+            window: Arc::clone(&self.window),
+            event_proxy: self.event_proxy.clone(),
+            context: self.context.clone(),
+        }
+    }
+}
+
+/// Resolve call `id` through a webview holder; used by `emit!`
+pub fn send_js<T: Serialize>(webview: &WebviewContext, id: i32, value: T) {
+    send_response(webview, &IpcResponse::from_value(id, value));
+}
+
+/// Reject call `id` through a webview holder; used by `emit_err!`
+pub fn send_error(webview: &WebviewContext, id: i32, error: impl Into<IpcError>) {
+    send_response(webview, &IpcResponse::err(id, error));
+}
+
+fn send_response(webview: &WebviewContext, response: &IpcResponse) {
+    let Some(ref webview) = *webview.lock() else {
+        log::warn!(
+            "Webview not initialized; dropping reply to call {}",
+            response.id
+        );
+        return;
+    };
+    if let Err(e) = taocket_ipc::send_response(webview, response) {
+        log::error!("Failed to reply to call {}: {}", response.id, e);
+    }
+}
+
+// ============================================================================
+// Config
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+    pub with_decorations: bool,
+    pub dev_url: Option<String>,
+    pub build_path: PathBuf,
+    pub with_devtools: bool,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            with_decorations: false,
+            dev_url: None,
+            build_path: PathBuf::from("frontend/build"),
+            with_devtools: true,
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn development(url: impl Into<String>) -> Self {
+        Self {
+            dev_url: Some(url.into()),
+            with_devtools: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn production(build_path: impl Into<PathBuf>) -> Self {
+        Self {
+            dev_url: None,
+            build_path: build_path.into(),
+            with_devtools: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_development(&self) -> bool {
+        self.dev_url.is_some()
+    }
+
+    pub fn is_production(&self) -> bool {
+        !self.is_development()
+    }
+
+    /// Equivalent `TaocketBuilder`; like the old builder it doesn't start
+    /// the WebSocket server
+    pub fn into_builder<E: CustomEvent>(self) -> TaocketBuilder<NoAssets, E> {
+        let mut config = TaocketConfigBuilder::new()
+            .build_path(self.build_path)
+            .devtools(self.with_devtools);
+        if let Some(url) = &self.dev_url {
+            config = config.dev_url(url.clone());
+        }
+        TaocketBuilder::from_config(config.build(), None)
+            .dev_url(self.dev_url)
+            .decorations(self.with_decorations)
+            .websocket(false)
+    }
+}
+
+// ============================================================================
+// Builder
+// ============================================================================
+
+pub struct AppBuilder<E: CustomEvent = ()> {
+    builder: TaocketBuilder<NoAssets, E>,
+}
+
+impl<E: CustomEvent> AppBuilder<E> {
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            builder: config.into_builder(),
+        }
+    }
+
+    /// Customise the underlying `TaocketBuilder`, e.g. to add commands
+    pub fn configure<C>(mut self, configure: C) -> Self
+    where
+        C: FnOnce(TaocketBuilder<NoAssets, E>) -> TaocketBuilder<NoAssets, E>,
+    {
+        self.builder = configure(self.builder);
+        self
+    }
+
+    pub fn run<F>(self, handler: F) -> wry::Result<()>
+    where
+        E: DeserializeOwned + Serialize,
+        F: Fn(Payload<E>, HandlerContext<E>) + Send + 'static,
+    {
+        self.run_with_setup(|_| {}, handler)
+    }
+
+    /// Run `setup` on the window before the webview is created
+    pub fn run_with_setup<F, S>(self, setup: S, handler: F) -> wry::Result<()>
+    where
+        E: DeserializeOwned + Serialize,
+        F: Fn(Payload<E>, HandlerContext<E>) + Send + 'static,
+        S: FnOnce(&Window),
+    {
+        self.builder.run(
+            |window, _hotkeys, _config| setup(window),
+            move |payload, ctx: WindowContext<E>| match ctx.event_proxy().cloned() {
+                Some(proxy) => handler(payload, HandlerContext::new(ctx, proxy)),
+                None => log::error!("Legacy handler called without an event loop proxy"),
+            },
+            |_, _, _, _| {},
+            |_, _| {},
+        )
+    }
+}
+
+pub struct App;
+
+impl App {
+    /// Run with a no-op handler; named `new` for compatibility
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(config: AppConfig) -> wry::Result<()> {
+        AppBuilder::<()>::new(config).run(|_payload, _ctx| {
+            // No-op default handler
+        })
+    }
+
+    pub fn builder<E: CustomEvent>(config: AppConfig) -> AppBuilder<E> {
+        AppBuilder::new(config)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    #[test]
+    fn test_emit_macros_take_a_webview_holder() {
+        // Old call sites pass `&ctx.webview`; without a webview the reply
+        // is dropped with a warning
+        #[allow(clippy::arc_with_non_send_sync)]
+        let webview: WebviewContext = Arc::new(Mutex::new(None));
+        crate::emit!(&webview, 1, serde_json::json!({ "ok": true }));
+        crate::emit!(&webview, 2);
+        crate::emit_err!(&webview, 3, "failed");
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config = AppConfig::default();
+        assert!(!config.with_decorations);
+        assert!(config.dev_url.is_none());
+        assert!(config.with_devtools);
+        assert_eq!(config.build_path, PathBuf::from("frontend/build"));
+    }
+
+    #[test]
+    fn test_development_config() {
+        let config = AppConfig::development("http://localhost:5173");
+        assert!(config.is_development());
+        assert!(!config.is_production());
+        assert_eq!(config.dev_url, Some("http://localhost:5173".into()));
+        assert!(config.with_devtools);
+    }
+
+    #[test]
+    fn test_production_config() {
+        let config = AppConfig::production("dist");
+        assert!(config.is_production());
+        assert!(!config.is_development());
+        assert!(config.dev_url.is_none());
+        assert!(!config.with_devtools);
+        assert_eq!(config.build_path, PathBuf::from("dist"));
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use crate::taocket_ipc::{IpcError, IpcErrorKind};
+
+// ============================================================================
+// Limits
+// ============================================================================
+
+/// Size and nesting limits for incoming IPC and WebSocket messages
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Limits {
+    /// Largest IPC message body, in bytes
+    pub max_ipc_message_size: usize,
+    /// Largest request body of a binary command, in bytes
+    pub max_binary_message_size: usize,
+    /// Largest WebSocket message, in bytes
+    pub max_ws_message_size: usize,
+    /// Deepest nesting of arrays and objects in a JSON message
+    pub max_json_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_ipc_message_size: 16 * 1024 * 1024,
+            max_binary_message_size: 64 * 1024 * 1024,
+            max_ws_message_size: 1024 * 1024,
+            max_json_depth: 64,
+        }
+    }
+}
+
+impl Limits {
+    pub fn max_ipc_message_size(mut self, bytes: usize) -> Self {
+        self.max_ipc_message_size = bytes;
+        self
+    }
+
+    pub fn max_binary_message_size(mut self, bytes: usize) -> Self {
+        self.max_binary_message_size = bytes;
+        self
+    }
+
+    pub fn max_ws_message_size(mut self, bytes: usize) -> Self {
+        self.max_ws_message_size = bytes;
+        self
+    }
+
+    pub fn max_json_depth(mut self, depth: usize) -> Self {
+        self.max_json_depth = depth;
+        self
+    }
+
+    /// Check an IPC body before it is decoded
+    pub fn check_ipc(&self, body: &str) -> Result<(), IpcError> {
+        if body.len() > self.max_ipc_message_size {
+            return Err(IpcError::with_kind(
+                IpcErrorKind::LimitExceeded,
+                format!(
+                    "Message of {} bytes exceeds the limit of {} bytes",
+                    body.len(),
+                    self.max_ipc_message_size
+                ),
+            ));
+        }
+        self.check_depth(body)
+    }
+
+    /// Check the body size of a binary command request before it is dispatched
+    pub fn check_binary(&self, len: usize) -> Result<(), IpcError> {
+        if len > self.max_binary_message_size {
+            return Err(IpcError::with_kind(
+                IpcErrorKind::LimitExceeded,
+                format!(
+                    "Binary request of {} bytes exceeds the limit of {} bytes",
+                    len, self.max_binary_message_size
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reject JSON nested deeper than `max_json_depth`
+    pub fn check_depth(&self, json: &str) -> Result<(), IpcError> {
+        if exceeds_depth(json, self.max_json_depth) {
+            return Err(IpcError::with_kind(
+                IpcErrorKind::LimitExceeded,
+                format!(
+                    "Message is nested deeper than {} levels",
+                    self.max_json_depth
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `json` nests arrays and objects deeper than `max`; scans the raw
+/// text so oversized input is never parsed
+pub fn exceeds_depth(json: &str, max: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in json.bytes() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    false
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeds_depth() {
+        assert!(!exceeds_depth(r#"{"a":[1,{"b":2}]}"#, 3));
+        assert!(exceeds_depth(r#"{"a":[1,{"b":2}]}"#, 2));
+        assert!(!exceeds_depth(r#"{"a":"[[[[\"{{{{"}"#, 1));
+        assert!(!exceeds_depth("plain text", 0));
+    }
+
+    #[test]
+    fn test_check_ipc() {
+        let limits = Limits::default().max_ipc_message_size(16).max_json_depth(2);
+        assert!(limits.check_ipc(r#"{"a":[1]}"#).is_ok());
+
+        let err = limits.check_ipc(r#"{"a":[[1]]}"#).unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::LimitExceeded);
+
+        let err = limits.check_ipc(r#"{"a":"0123456789"}"#).unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::LimitExceeded);
+        assert!(err.message.contains("16 bytes"));
+    }
+}
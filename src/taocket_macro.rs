@@ -1,5 +1,9 @@
 use wry::WebView;
 
+// Lets the macros below log from crates that don't depend on `log`
+#[doc(hidden)]
+pub use log as __log;
+
 pub fn with_callback(webview: &WebView, script: &str) {
     let _ = webview.evaluate_script_with_callback(script, |result| println!("{}", result));
 }
@@ -21,7 +25,11 @@ macro_rules! callback {
             );
 
             if let Err(e) = $webview.evaluate_script(&script) {
-                eprintln!("Failed to emit '{}' event: {}", $event_name, e);
+                $crate::taocket_macro::__log::error!(
+                    "Failed to emit '{}' event: {}",
+                    $event_name,
+                    e
+                );
             }
         }
     }};
@@ -33,6 +41,50 @@ macro_rules! emit_js {
         $webview.evaluate_script(&value);
     };
 }
+/// Resolve call `$id` through a webview holder such as
+/// `HandlerContext::webview`; `emit!(webview, id)` resolves with `null`
+#[macro_export]
+macro_rules! emit {
+    ($webview:expr, $id:expr, $value:expr) => {
+        $crate::taocket_legacy::send_js($webview, $id, $value)
+    };
+    ($webview:expr, $id:expr) => {
+        $crate::taocket_legacy::send_js($webview, $id, ())
+    };
+}
+
+/// Reject call `$id` through a webview holder with a message
+#[macro_export]
+macro_rules! emit_err {
+    ($webview:expr, $id:expr, $msg:expr) => {
+        $crate::taocket_legacy::send_error($webview, $id, $msg.to_string())
+    };
+}
+
+/// Resolve call `$id` through a `WindowContext`; `resolve!(ctx, id)`
+/// resolves with `null`
+#[macro_export]
+macro_rules! resolve {
+    ($ctx:expr, $id:expr, $value:expr) => {{
+        if let Err(e) = $ctx.resolve($id, $value) {
+            $crate::taocket_macro::__log::error!("Failed to resolve call {}: {}", $id, e);
+        }
+    }};
+    ($ctx:expr, $id:expr) => {
+        $crate::resolve!($ctx, $id, ())
+    };
+}
+
+/// Reject call `$id` through a `WindowContext` with a message
+#[macro_export]
+macro_rules! reject {
+    ($ctx:expr, $id:expr, $msg:expr) => {{
+        if let Err(e) = $ctx.reject($id, $msg.to_string()) {
+            $crate::taocket_macro::__log::error!("Failed to reject call {}: {}", $id, e);
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! create_struct {
     ($name:ident) => {
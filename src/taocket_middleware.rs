@@ -0,0 +1,386 @@
+use parking_lot::Mutex;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    CustomEvent,
+    taocket_context::WindowContext,
+    taocket_ipc::{IpcError, IpcErrorKind, IpcResponse, IpcResult},
+    taocket_window::IpcKind,
+};
+
+// ============================================================================
+// Extensions
+// ============================================================================
+
+/// Typed values attached to a call by middleware, readable by handlers
+/// through `WindowContext::extensions`
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a value, returning the previous one of the same type
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok().map(|boxed| *boxed))
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|boxed| *boxed))
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+pub type SharedExtensions = Arc<Mutex<Extensions>>;
+
+// ============================================================================
+// Middleware
+// ============================================================================
+
+/// Incoming IPC message as seen by middleware
+#[derive(Debug, Clone)]
+pub struct IpcRequest {
+    pub kind: IpcKind,
+    /// Label of the window that sent the message
+    pub window: String,
+    /// Call id, if the message carried a readable one
+    pub id: Option<i32>,
+    /// Event, command or topic name
+    pub event: Option<String>,
+    /// URL of the page that sent the message
+    pub url: String,
+    /// Raw JSON body
+    pub body: String,
+}
+
+/// Layer around IPC dispatch, registered with `TaocketBuilder::middleware`;
+/// binary commands on the custom protocol don't go through it
+pub trait Middleware<E: CustomEvent>: Send + Sync + 'static {
+    /// Runs before dispatch, in registration order; an error rejects the
+    /// call without running later layers or the handler
+    fn before(&self, request: &IpcRequest, ctx: &WindowContext<E>) -> Result<(), IpcError> {
+        let _ = (request, ctx);
+        Ok(())
+    }
+
+    /// Runs in reverse order once the call is settled, including rejections
+    /// and calls the frontend cancelled. User events handled by the `run`
+    /// handler need not be answered, so it never runs for them
+    fn after(&self, request: &IpcRequest, response: &IpcResponse, extensions: &Extensions) {
+        let _ = (request, response, extensions);
+    }
+}
+
+/// Calls still tracked after this long are assumed to never be answered
+const STALE_AFTER: Duration = Duration::from_secs(300);
+const PRUNE_ABOVE: usize = 256;
+
+struct InFlight {
+    request: IpcRequest,
+    extensions: SharedExtensions,
+    started: Instant,
+}
+
+/// Registered middleware plus the calls waiting for a reply
+pub struct MiddlewareStack<E: CustomEvent> {
+    layers: Vec<Box<dyn Middleware<E>>>,
+    /// Keyed by window label and call id, as ids are only unique per page
+    in_flight: Mutex<HashMap<(String, i32), InFlight>>,
+}
+
+impl<E: CustomEvent> MiddlewareStack<E> {
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn push(&mut self, middleware: impl Middleware<E>) {
+        self.layers.push(Box::new(middleware));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Run every `before` hook for `request`; calls that are sure to get a
+    /// reply are `track`ed until it is sent, so `after` runs for them
+    pub(crate) fn begin(
+        &self,
+        request: IpcRequest,
+        ctx: &WindowContext<E>,
+        track: bool,
+    ) -> Result<(), IpcError> {
+        if self.layers.is_empty() {
+            return Ok(());
+        }
+        if let Some(id) = request.id.filter(|_| track) {
+            let mut in_flight = self.in_flight.lock();
+            if in_flight.len() > PRUNE_ABOVE {
+                in_flight.retain(|_, call| call.started.elapsed() < STALE_AFTER);
+            }
+            in_flight.insert(
+                (request.window.clone(), id),
+                InFlight {
+                    request: request.clone(),
+                    extensions: Arc::clone(ctx.shared_extensions()),
+                    started: Instant::now(),
+                },
+            );
+        }
+        for layer in &self.layers {
+            layer.before(&request, ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Run every `after` hook for a call of window `label` that settled
+    pub(crate) fn finish(&self, label: &str, response: &IpcResponse) {
+        if self.layers.is_empty() {
+            return;
+        }
+        let key = (label.to_string(), response.id);
+        let Some(call) = self.in_flight.lock().remove(&key) else {
+            return;
+        };
+        let extensions = call.extensions.lock();
+        for layer in self.layers.iter().rev() {
+            layer.after(&call.request, response, &extensions);
+        }
+    }
+
+    /// Settle a call the frontend cancelled, for which no reply is sent
+    pub(crate) fn abandon(&self, label: &str, id: i32) {
+        self.finish(
+            label,
+            &IpcResponse::err(
+                id,
+                IpcError::with_kind(IpcErrorKind::Cancelled, "Call cancelled by the frontend"),
+            ),
+        );
+    }
+}
+
+impl<E: CustomEvent> Default for MiddlewareStack<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Built-in Middleware
+// ============================================================================
+
+/// Logs every incoming call and its outcome
+#[derive(Debug, Clone)]
+pub struct RequestLogger {
+    level: log::Level,
+}
+
+impl RequestLogger {
+    pub fn new() -> Self {
+        Self {
+            level: log::Level::Info,
+        }
+    }
+
+    pub fn level(mut self, level: log::Level) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+impl Default for RequestLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: CustomEvent> Middleware<E> for RequestLogger {
+    fn before(&self, request: &IpcRequest, _ctx: &WindowContext<E>) -> Result<(), IpcError> {
+        log::log!(
+            self.level,
+            "IPC {:?} '{}' #{}",
+            request.kind,
+            request.event.as_deref().unwrap_or("?"),
+            request
+                .id
+                .map_or_else(|| "-".to_string(), |id| id.to_string()),
+        );
+        Ok(())
+    }
+
+    fn after(&self, request: &IpcRequest, response: &IpcResponse, _extensions: &Extensions) {
+        let event = request.event.as_deref().unwrap_or("?");
+        match &response.result {
+            IpcResult::Ok(_) => log::log!(self.level, "IPC '{}' #{} ok", event, response.id),
+            IpcResult::Err(e) => log::log!(
+                self.level,
+                "IPC '{}' #{} failed ({:?}): {}",
+                event,
+                response.id,
+                e.kind,
+                e.message
+            ),
+        }
+    }
+}
+
+/// Start time of a call, attached by `LatencyTimer`
+#[derive(Debug, Clone, Copy)]
+pub struct CallStart(pub Instant);
+
+type LatencyCallback = Box<dyn Fn(&IpcRequest, Duration) + Send + Sync>;
+
+/// Measures the time from receiving a call to settling it
+pub struct LatencyTimer {
+    warn_above: Option<Duration>,
+    callback: Option<LatencyCallback>,
+}
+
+impl LatencyTimer {
+    pub fn new() -> Self {
+        Self {
+            warn_above: None,
+            callback: None,
+        }
+    }
+
+    /// Log a warning for calls slower than `threshold`
+    pub fn warn_above(mut self, threshold: Duration) -> Self {
+        self.warn_above = Some(threshold);
+        self
+    }
+
+    /// Receive every measurement, e.g. to feed metrics
+    pub fn on_measure<C>(mut self, callback: C) -> Self
+    where
+        C: Fn(&IpcRequest, Duration) + Send + Sync + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+}
+
+impl Default for LatencyTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: CustomEvent> Middleware<E> for LatencyTimer {
+    fn before(&self, _request: &IpcRequest, ctx: &WindowContext<E>) -> Result<(), IpcError> {
+        ctx.extensions().insert(CallStart(Instant::now()));
+        Ok(())
+    }
+
+    fn after(&self, request: &IpcRequest, _response: &IpcResponse, extensions: &Extensions) {
+        let Some(CallStart(started)) = extensions.get::<CallStart>() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        let event = request.event.as_deref().unwrap_or("?");
+        match self.warn_above {
+            Some(threshold) if elapsed > threshold => {
+                log::warn!("IPC '{}' took {:?}", event, elapsed)
+            }
+            _ => log::debug!("IPC '{}' took {:?}", event, elapsed),
+        }
+        if let Some(callback) = &self.callback {
+            callback(request, elapsed);
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct User(&'static str);
+
+    #[test]
+    fn test_extensions() {
+        let mut extensions = Extensions::new();
+        assert!(extensions.insert(User("a")).is_none());
+        assert_eq!(extensions.insert(User("b")), Some(User("a")));
+        assert_eq!(extensions.get::<User>(), Some(&User("b")));
+        assert!(!extensions.contains::<CallStart>());
+
+        extensions.get_mut::<User>().unwrap().0 = "c";
+        assert_eq!(extensions.remove::<User>(), Some(User("c")));
+        assert!(extensions.get::<User>().is_none());
+    }
+
+    #[test]
+    fn test_latency_timer_measures_from_call_start() {
+        let measured = Arc::new(Mutex::new(None));
+        let timer = LatencyTimer::new().on_measure({
+            let measured = Arc::clone(&measured);
+            move |request, elapsed| *measured.lock() = Some((request.event.clone(), elapsed))
+        });
+
+        let request = IpcRequest {
+            kind: IpcKind::Invoke,
+            window: "main".into(),
+            id: Some(1),
+            event: Some("save".into()),
+            url: "taocket://localhost".into(),
+            body: String::new(),
+        };
+        let mut extensions = Extensions::new();
+        Middleware::<()>::after(
+            &timer,
+            &request,
+            &IpcResponse::ok(1, ().into()),
+            &extensions,
+        );
+        assert!(measured.lock().is_none());
+
+        extensions.insert(CallStart(Instant::now()));
+        Middleware::<()>::after(
+            &timer,
+            &request,
+            &IpcResponse::ok(1, ().into()),
+            &extensions,
+        );
+        assert_eq!(measured.lock().as_ref().unwrap().0.as_deref(), Some("save"));
+    }
+}
@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc, time::Instant};
+use tao::{
+    event::WindowEvent,
+    window::{Window, WindowId},
+};
+use ts_rs::TS;
+
+use crate::{
+    taocket_cancel::CancelRegistry,
+    taocket_context::WebviewContext,
+    taocket_geometry::{GeometryTracker, WindowGeometry},
+    taocket_native_events::{NativeEvents, NativeWindowEvent},
+};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Label of the window created by `TaocketBuilder::run`; closing it exits
+pub const MAIN_WINDOW: &str = "main";
+
+/// Options for a secondary window, opened with `TaocketBuilder::window`,
+/// `WindowContext::open_window` or `taocket.windows.open` in JS
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[serde(default)]
+pub struct WindowOptions {
+    /// Unique name used to target the window
+    pub label: String,
+    /// Page to load; paths are resolved against the app's start URL and
+    /// `None` loads the start page itself. Windows opened from JS can't leave
+    /// the app's origin, and pages elsewhere get no IPC bridge
+    #[ts(optional)]
+    pub url: Option<String>,
+    #[ts(optional)]
+    pub title: Option<String>,
+    #[ts(optional)]
+    pub width: Option<f64>,
+    #[ts(optional)]
+    pub height: Option<f64>,
+    pub decorations: bool,
+    pub resizable: bool,
+    pub top_most: bool,
+    /// Defaults to the config's `devtools`
+    #[ts(optional)]
+    pub devtools: Option<bool>,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            label: String::new(),
+            url: None,
+            title: None,
+            width: None,
+            height: None,
+            decorations: true,
+            resizable: true,
+            top_most: false,
+            devtools: None,
+        }
+    }
+}
+
+impl WindowOptions {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn size(mut self, width: f64, height: f64) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    pub fn decorations(mut self, enabled: bool) -> Self {
+        self.decorations = enabled;
+        self
+    }
+
+    pub fn resizable(mut self, enabled: bool) -> Self {
+        self.resizable = enabled;
+        self
+    }
+
+    pub fn top_most(mut self, enabled: bool) -> Self {
+        self.top_most = enabled;
+        self
+    }
+
+    pub fn devtools(mut self, enabled: bool) -> Self {
+        self.devtools = Some(enabled);
+        self
+    }
+
+    /// URL to load, given the app's start URL
+    pub(crate) fn resolve_url(&self, start_url: &str) -> String {
+        match self.url.as_deref() {
+            None => start_url.to_string(),
+            Some(url) if url.contains("://") => url.to_string(),
+            Some(path) => format!(
+                "{}/{}",
+                start_url.trim_end_matches('/'),
+                path.trim_start_matches('/')
+            ),
+        }
+    }
+}
+
+/// Whether `url` shares the scheme and host of the app's start URL, counting
+/// the `http(s)://<scheme>.<host>` form Windows and Android serve custom
+/// protocols under; only such pages get the IPC bridge
+pub(crate) fn is_app_origin(url: &str, start_url: &str) -> bool {
+    let origin = |url: &str| {
+        let uri = url.parse::<wry::http::Uri>().ok()?;
+        Some((
+            uri.scheme_str()?.to_ascii_lowercase(),
+            uri.authority()?.as_str().to_ascii_lowercase(),
+        ))
+    };
+    match (origin(url), origin(start_url)) {
+        (Some(url), Some(start)) => {
+            url == start
+                || (matches!(url.0.as_str(), "http" | "https")
+                    && url.1 == format!("{}.{}", start.0, start.1))
+        }
+        _ => false,
+    }
+}
+
+/// Windows an event or script is sent to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Window(String),
+    All,
+}
+
+impl From<&str> for Target {
+    fn from(label: &str) -> Self {
+        Target::Window(label.to_string())
+    }
+}
+
+impl From<String> for Target {
+    fn from(label: String) -> Self {
+        Target::Window(label)
+    }
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// Open window with its webview, owned by the event loop
+pub(crate) struct WindowEntry {
+    pub window: Arc<Window>,
+    pub webview: WebviewContext,
+    pub cancels: CancelRegistry,
+    /// The page has `beforeclose` listeners to ask before closing
+    pub close_guard: bool,
+    /// Waiting for the page's answer to a close request
+    pub closing: bool,
+}
+
+/// Every open window by label, only touched on the UI thread
+#[derive(Default)]
+pub(crate) struct WindowRegistry {
+    windows: HashMap<String, WindowEntry>,
+    labels: HashMap<WindowId, String>,
+    geometry: Option<GeometryTracker>,
+    native_events: NativeEvents,
+}
+
+impl WindowRegistry {
+    pub fn contains(&self, label: &str) -> bool {
+        self.windows.contains_key(label)
+    }
+
+    pub fn insert(&mut self, label: String, entry: WindowEntry) {
+        self.labels.insert(entry.window.id(), label.clone());
+        self.windows.insert(label, entry);
+    }
+
+    pub fn get(&self, label: &str) -> Option<&WindowEntry> {
+        self.windows.get(label)
+    }
+
+    pub fn get_mut(&mut self, label: &str) -> Option<&mut WindowEntry> {
+        self.windows.get_mut(label)
+    }
+
+    pub fn label_of(&self, id: WindowId) -> Option<&str> {
+        self.labels.get(&id).map(String::as_str)
+    }
+
+    /// Windows matching `target`
+    pub fn targets<'a>(&'a self, target: &'a Target) -> impl Iterator<Item = &'a WindowEntry> {
+        self.windows
+            .iter()
+            .filter(move |(label, _)| match target {
+                Target::All => true,
+                Target::Window(wanted) => *label == wanted,
+            })
+            .map(|(_, entry)| entry)
+    }
+
+    /// Persist window geometry through `tracker`
+    pub fn track_geometry(&mut self, tracker: GeometryTracker) {
+        self.geometry = Some(tracker);
+    }
+
+    /// Geometry saved for `label` by a previous run
+    pub fn saved_geometry(&self, label: &str) -> Option<&WindowGeometry> {
+        self.geometry.as_ref()?.saved(label)
+    }
+
+    /// Record the current geometry of window `id` after it moved or resized
+    pub fn update_geometry(&mut self, id: WindowId) {
+        let (Some(tracker), Some(label)) = (self.geometry.as_mut(), self.labels.get(&id)) else {
+            return;
+        };
+        if let Some(entry) = self.windows.get(label) {
+            tracker.track(label, &entry.window);
+        }
+    }
+
+    /// Write settled geometry changes, or every pending one when `now` is
+    /// `None`
+    pub fn flush_geometry(&mut self, now: Option<Instant>) {
+        if let Some(tracker) = self.geometry.as_mut() {
+            match now {
+                Some(now) => tracker.flush_due(now),
+                None => tracker.flush(),
+            }
+        }
+    }
+
+    /// Native event to forward right away for `event` on window `label`;
+    /// moves and resizes are held back until `take_native_events`
+    pub fn translate_event(
+        &mut self,
+        label: &str,
+        event: &WindowEvent,
+        now: Instant,
+    ) -> Option<NativeWindowEvent> {
+        let entry = self.windows.get(label)?;
+        self.native_events
+            .translate(label, event, &entry.window, now)
+    }
+
+    /// Held back native events that have settled
+    pub fn take_native_events(&mut self, now: Instant) -> Vec<(String, NativeWindowEvent)> {
+        self.native_events.take_due(now)
+    }
+
+    /// Forget `label`, hiding its window and dropping its webview; handlers
+    /// may still hold the window, so it can outlive this call
+    pub fn remove(&mut self, label: &str) -> bool {
+        let Some(entry) = self.windows.remove(label) else {
+            return false;
+        };
+        if let Some(tracker) = self.geometry.as_mut() {
+            tracker.track(label, &entry.window);
+        }
+        self.labels.remove(&entry.window.id());
+        self.native_events.forget(label);
+        entry.cancels.cancel_all();
+        entry.window.set_visible(false);
+        entry.webview.lock().take();
+        true
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_url() {
+        let start = "taocket://localhost";
+        assert_eq!(WindowOptions::new("a").resolve_url(start), start);
+        assert_eq!(
+            WindowOptions::new("a")
+                .url("/settings.html")
+                .resolve_url("http://localhost:5173/"),
+            "http://localhost:5173/settings.html"
+        );
+        assert_eq!(
+            WindowOptions::new("a")
+                .url("https://example.com")
+                .resolve_url(start),
+            "https://example.com"
+        );
+    }
+
+    #[test]
+    fn test_is_app_origin() {
+        let start = "taocket://localhost";
+        assert!(is_app_origin(
+            &WindowOptions::new("a").resolve_url(start),
+            start
+        ));
+        assert!(is_app_origin(
+            &WindowOptions::new("a")
+                .url("settings.html")
+                .resolve_url(start),
+            start
+        ));
+        assert!(is_app_origin(
+            "http://localhost:5173/settings.html",
+            "http://localhost:5173/"
+        ));
+        assert!(!is_app_origin(
+            "http://localhost:8080/",
+            "http://localhost:5173/"
+        ));
+        assert!(!is_app_origin(
+            &WindowOptions::new("a")
+                .url("https://example.com")
+                .resolve_url(start),
+            start
+        ));
+        assert!(!is_app_origin("not a url", start));
+        assert!(is_app_origin("http://taocket.localhost/index.html", start));
+        assert!(is_app_origin("https://taocket.localhost", start));
+        assert!(!is_app_origin("http://evil.localhost", start));
+    }
+
+    #[test]
+    fn test_options_defaults() {
+        let options: WindowOptions = serde_json::from_str(r#"{"label":"settings"}"#).unwrap();
+        assert_eq!(options, WindowOptions::new("settings"));
+        assert!(options.decorations);
+        assert!(options.resizable);
+    }
+}
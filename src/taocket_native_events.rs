@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    mem,
+    time::{Duration, Instant},
+};
+use tao::{
+    event::WindowEvent,
+    window::{Theme, Window},
+};
+use ts_rs::TS;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Topic native window events are emitted on, see `taocket.window.onEvent`
+pub const WINDOW_EVENT_TOPIC: &str = "taocket:window";
+
+/// How long moves and resizes settle before they are forwarded
+pub const NATIVE_EVENT_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Native window event forwarded to the frontend and the
+/// `TaocketBuilder::on_window_event` callback; sizes and positions are logical
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[serde(tag = "type", content = "value")]
+pub enum NativeWindowEvent {
+    /// New inner size
+    Resized {
+        width: f64,
+        height: f64,
+    },
+    /// New outer position
+    Moved {
+        x: f64,
+        y: f64,
+    },
+    Focused,
+    Blurred,
+    Minimized,
+    /// Shown again after being minimized
+    Restored,
+    ThemeChanged(WindowTheme),
+    ScaleFactorChanged(f64),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowTheme {
+    Light,
+    Dark,
+}
+
+impl From<Theme> for WindowTheme {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::Dark => WindowTheme::Dark,
+            _ => WindowTheme::Light,
+        }
+    }
+}
+
+// ============================================================================
+// Translation
+// ============================================================================
+
+/// Pending debounced event of one window
+struct Pending {
+    label: String,
+    event: NativeWindowEvent,
+    at: Instant,
+}
+
+/// Turns tao window events into `NativeWindowEvent`s, holding back moves and
+/// resizes until they settle; owned by the event loop
+#[derive(Default)]
+pub(crate) struct NativeEvents {
+    pending: Vec<Pending>,
+    minimized: HashMap<String, bool>,
+}
+
+impl NativeEvents {
+    /// Events to forward right away for `event` on window `label`; moves and
+    /// resizes are queued for `take_due` instead
+    pub fn translate(
+        &mut self,
+        label: &str,
+        event: &WindowEvent,
+        window: &Window,
+        now: Instant,
+    ) -> Option<NativeWindowEvent> {
+        let scale = window.scale_factor();
+        match event {
+            WindowEvent::Resized(size) => {
+                let minimized = window.is_minimized();
+                let was_minimized = self.minimized.insert(label.to_string(), minimized);
+                if was_minimized.unwrap_or(false) != minimized {
+                    return Some(if minimized {
+                        NativeWindowEvent::Minimized
+                    } else {
+                        NativeWindowEvent::Restored
+                    });
+                }
+                if !minimized {
+                    let size = size.to_logical::<f64>(scale);
+                    self.debounce(
+                        label,
+                        NativeWindowEvent::Resized {
+                            width: size.width,
+                            height: size.height,
+                        },
+                        now,
+                    );
+                }
+                None
+            }
+            WindowEvent::Moved(position) => {
+                let position = position.to_logical::<f64>(scale);
+                self.debounce(
+                    label,
+                    NativeWindowEvent::Moved {
+                        x: position.x,
+                        y: position.y,
+                    },
+                    now,
+                );
+                None
+            }
+            WindowEvent::Focused(true) => Some(NativeWindowEvent::Focused),
+            WindowEvent::Focused(false) => Some(NativeWindowEvent::Blurred),
+            WindowEvent::ThemeChanged(theme) => {
+                Some(NativeWindowEvent::ThemeChanged((*theme).into()))
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                Some(NativeWindowEvent::ScaleFactorChanged(*scale_factor))
+            }
+            _ => None,
+        }
+    }
+
+    /// Queue `event`, replacing a pending event of the same kind
+    fn debounce(&mut self, label: &str, event: NativeWindowEvent, now: Instant) {
+        let kind = mem::discriminant(&event);
+        self.pending
+            .retain(|p| p.label != label || mem::discriminant(&p.event) != kind);
+        self.pending.push(Pending {
+            label: label.to_string(),
+            event,
+            at: now,
+        });
+    }
+
+    /// Queued events that settled for `NATIVE_EVENT_DEBOUNCE`
+    pub fn take_due(&mut self, now: Instant) -> Vec<(String, NativeWindowEvent)> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        let (due, waiting) = mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|p| now.duration_since(p.at) >= NATIVE_EVENT_DEBOUNCE);
+        self.pending = waiting;
+        due.into_iter()
+            .map(|p: Pending| (p.label, p.event))
+            .collect()
+    }
+
+    /// Drop the state of a closed window
+    pub fn forget(&mut self, label: &str) {
+        self.pending.retain(|p| p.label != label);
+        self.minimized.remove(label);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resized(width: f64) -> NativeWindowEvent {
+        NativeWindowEvent::Resized {
+            width,
+            height: 100.0,
+        }
+    }
+
+    #[test]
+    fn test_debounce_keeps_latest_per_kind() {
+        let mut events = NativeEvents::default();
+        let start = Instant::now();
+        events.debounce("main", resized(100.0), start);
+        events.debounce("main", NativeWindowEvent::Moved { x: 1.0, y: 2.0 }, start);
+        events.debounce("other", resized(50.0), start);
+        events.debounce("main", resized(200.0), start + NATIVE_EVENT_DEBOUNCE / 2);
+
+        let due = events.take_due(start + NATIVE_EVENT_DEBOUNCE);
+        assert_eq!(
+            due,
+            vec![
+                (
+                    "main".to_string(),
+                    NativeWindowEvent::Moved { x: 1.0, y: 2.0 }
+                ),
+                ("other".to_string(), resized(50.0)),
+            ]
+        );
+
+        let due = events.take_due(start + NATIVE_EVENT_DEBOUNCE * 2);
+        assert_eq!(due, vec![("main".to_string(), resized(200.0))]);
+        assert!(
+            events
+                .take_due(start + NATIVE_EVENT_DEBOUNCE * 3)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_forget() {
+        let mut events = NativeEvents::default();
+        let start = Instant::now();
+        events.debounce("settings", resized(100.0), start);
+        events.forget("settings");
+        assert!(events.take_due(start + NATIVE_EVENT_DEBOUNCE).is_empty());
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let json =
+            serde_json::to_value(NativeWindowEvent::ThemeChanged(WindowTheme::Dark)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "ThemeChanged", "value": "dark" })
+        );
+
+        let json = serde_json::to_value(NativeWindowEvent::Focused).unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "Focused" }));
+    }
+}
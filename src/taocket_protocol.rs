@@ -5,7 +5,7 @@ pub fn handle_custom_protocol(request: Request<Vec<u8>>, root: &PathBuf) -> Resp
     match get_response(request, root) {
         Ok(response) => response.map(Into::into),
         Err(e) => {
-            eprintln!("Protocol error: {}", e);
+            log::error!("Protocol error: {}", e);
             Response::builder()
                 .header(CONTENT_TYPE, "text/plain")
                 .status(500)
@@ -1,21 +1,41 @@
-use crossbeam_channel::{self, Sender};
+use crossbeam_channel::{self, Receiver, Sender};
 use global_hotkey::HotKeyState::Released;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
-use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+use std::{collections::HashMap, future::Future, path::PathBuf, rc::Rc, sync::Arc, time::Duration};
 use tao::{
-    dpi::LogicalSize,
+    dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
     event::Event,
-    event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy},
-    window::{Window, WindowBuilder},
+    event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy, EventLoopWindowTarget},
+    platform::run_return::EventLoopExtRunReturn,
+    window::{Icon, ResizeDirection, Window, WindowBuilder},
 };
-use wry::{NewWindowFeatures, NewWindowResponse, WebView, WebViewBuilder, http::Request};
+use ts_rs::TS;
+use wry::{NewWindowFeatures, NewWindowResponse, WebViewBuilder, http::Request};
 
 use crate::{
-    CustomEvent, callback,
-    taocket_config::TaocketConfig,
-    taocket_context::{Clients, WebviewContext, WindowContext},
+    CustomEvent,
+    taocket_binary::{self, BinaryReply, BinaryRequest, BinaryResponse, BinaryRouter},
+    taocket_bindings,
+    taocket_cancel::CancelRegistry,
+    taocket_capability::{self, Access, Capability},
+    taocket_command::{CommandCall, CommandRouter, Dispatched},
+    taocket_config::{DoubleClickAction, TaocketConfig, WindowSize},
+    taocket_context::{
+        AsyncContext, Clients, LoopHandle, ReplyBatch, WebviewContext, WindowContext,
+    },
+    taocket_event::{self, EventBus},
+    taocket_geometry::{GeometryTracker, MonitorRect},
     taocket_hotkey::{HotkeyAndFunc, TaocketHotkeyManager},
+    taocket_ipc::{
+        self, Handshake, IpcError, IpcErrorKind, IpcResponse, IpcResult, PROTOCOL_VERSION,
+    },
+    taocket_limits::Limits,
+    taocket_middleware::{IpcRequest, Middleware, MiddlewareStack},
+    taocket_multiwindow::{
+        MAIN_WINDOW, Target, WindowEntry, WindowOptions, WindowRegistry, is_app_origin,
+    },
+    taocket_native_events::{NativeWindowEvent, WINDOW_EVENT_TOPIC},
     taocket_protocol, taocket_utils,
     ws::{self, Message},
 };
@@ -38,6 +58,16 @@ impl<T: rust_embed::RustEmbed + Send + Sync + std::fmt::Debug> AssetProvider for
     }
 }
 
+/// Asset provider for apps serving their frontend from `build_path` only
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoAssets;
+
+impl AssetProvider for NoAssets {
+    fn get(&self, _path: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
 // ============================================================================
 // Configuration
 // ============================================================================
@@ -47,6 +77,8 @@ pub struct WindowAttrs {
     pub build_path: String,
 
     pub with_devtools: bool,
+    pub with_decorations: bool,
+    pub with_websocket: bool,
     pub websocket_port: u16,
 }
 
@@ -56,6 +88,8 @@ impl std::fmt::Debug for WindowAttrs {
             .field("dev_url", &self.dev_url)
             .field("build_path", &self.build_path)
             .field("with_devtools", &self.with_devtools)
+            .field("with_decorations", &self.with_decorations)
+            .field("with_websocket", &self.with_websocket)
             .field("websocket_port", &self.websocket_port)
             .finish()
     }
@@ -68,6 +102,8 @@ impl Clone for WindowAttrs {
             build_path: self.build_path.clone(),
 
             with_devtools: self.with_devtools,
+            with_decorations: self.with_decorations,
+            with_websocket: self.with_websocket,
             websocket_port: self.websocket_port,
         }
     }
@@ -77,28 +113,186 @@ impl Clone for WindowAttrs {
 // Message Types
 // ============================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
 pub struct WindowAttrPayload {
     #[serde(rename = "type")]
     pub attr_type: &'static str,
     pub value: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
 pub struct Payload<T> {
     pub id: i32,
     pub event: T,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
     pub value: Option<serde_json::Value>,
+    /// `event.value` as sent, where `invoke` puts its argument; kept even
+    /// when `T` doesn't capture it
+    #[serde(skip)]
+    #[ts(skip)]
+    event_value: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl<T> Payload<T> {
+    /// Decode `value` into `V`; a missing value decodes like `null`, so
+    /// `Option<_>` and `()` accept it. Without `value` these helpers read the
+    /// event's own `value` as sent
+    pub fn value_as<V: DeserializeOwned>(&self) -> Result<V, IpcError> {
+        let value = self.sent_value().unwrap_or_default();
+        taocket_ipc::decode_value(value).map_err(value_error)
+    }
+
+    /// Decode `value` into `V`, rejecting a missing or `null` value
+    pub fn required<V: DeserializeOwned>(&self) -> Result<V, IpcError> {
+        self.optional()?.ok_or_else(|| {
+            IpcError::with_kind(IpcErrorKind::InvalidPayload, "Missing value").path("value")
+        })
+    }
+
+    /// Decode `value` into `V`, or `None` when it is missing or `null`
+    pub fn optional<V: DeserializeOwned>(&self) -> Result<Option<V>, IpcError> {
+        match self.sent_value() {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(value) => taocket_ipc::decode_value(value)
+                .map(Some)
+                .map_err(value_error),
+        }
+    }
+
+    /// `value`, or else the `value` of the tagged event
+    fn sent_value(&self) -> Option<serde_json::Value> {
+        self.value.clone().or_else(|| self.event_value.clone())
+    }
+}
+
+/// Decode a user event message, keeping the raw `event.value` for the
+/// `Payload` helpers
+fn decode_user_message<E: DeserializeOwned>(body: &str) -> Result<IpcMessage<E>, IpcError> {
+    let mut msg = taocket_ipc::decode_str::<IpcMessage<E>>(body)?;
+    msg.payload.event_value = taocket_ipc::decode_str::<RawEventValue>(body)
+        .ok()
+        .and_then(|raw| raw.payload.event.value);
+    Ok(msg)
+}
+
+/// Re-root a decode error of `Payload::value` at `value`
+fn value_error(error: IpcError) -> IpcError {
+    let path = match error.path.as_deref() {
+        Some(path) if path.starts_with('[') => format!("value{}", path),
+        Some(path) => format!("value.{}", path),
+        None => "value".to_string(),
+    };
+    IpcError {
+        kind: IpcErrorKind::InvalidPayload,
+        message: format!("Invalid value: {}", error.message),
+        event: error.event,
+        path: Some(path),
+    }
+}
+
+/// Namespace of an IPC message; built-in window commands never share a
+/// namespace with user events, so user variants can't be shadowed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum IpcKind {
+    /// User commands and events (`invoke`)
+    #[default]
+    Invoke,
+    /// Built-in window commands (`taocket.window.*`)
+    Window,
+    /// Topic events for Rust listeners (`taocket.emit`)
+    Emit,
+    /// The frontend abandoned the call with `payload.id`
+    Cancel,
+    /// Protocol version announced by `init.js` on page load
+    Handshake,
+    /// Several messages sent as one; `event.value` holds the messages
+    Batch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
 pub struct IpcMessage<T> {
+    #[serde(default)]
+    pub kind: IpcKind,
     pub payload: Payload<T>,
 }
 
+/// Event of a batch message: `{ type: "Batch", value: [<message>, ...] }`
+#[derive(Debug, Deserialize)]
+struct BatchCalls {
+    value: Vec<Box<serde_json::value::RawValue>>,
+}
+
+/// Just the `event.value` of a message
+#[derive(Debug, Deserialize)]
+struct RawEventValue {
+    payload: RawEventPayload,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawEventPayload {
+    event: RawEvent,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawEvent {
+    #[serde(default)]
+    value: Option<serde_json::Value>,
+}
+
+/// Just the calls of a batch message, read leniently to reject them
+#[derive(Debug, Deserialize)]
+struct BatchMembers {
+    kind: Option<IpcKind>,
+    payload: BatchMembersPayload,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchMembersPayload {
+    event: BatchCalls,
+}
+
+/// Rejections of every call of a batch message whose id is readable; empty
+/// for other messages
+fn member_rejections(body: &str, error: &IpcError) -> Vec<IpcResponse> {
+    let Ok(batch) = serde_json::from_str::<BatchMembers>(body) else {
+        return Vec::new();
+    };
+    if batch.kind != Some(IpcKind::Batch) {
+        return Vec::new();
+    }
+    batch
+        .payload
+        .event
+        .value
+        .iter()
+        .filter_map(|call| serde_json::from_str::<MessageId>(call.get()).ok())
+        .map(|call| IpcResponse::err(call.payload.id, error.clone()))
+        .collect()
+}
+
+/// Just the call id of a message; every other field is skipped unparsed
+#[derive(Debug, Deserialize)]
+struct MessageId {
+    payload: PayloadId,
+}
+
+#[derive(Debug, Deserialize)]
+struct PayloadId {
+    id: i32,
+}
+
+/// Just the `kind` of a message, read before decoding the payload; frontends
+/// predating `kind` leave it out
+#[derive(Debug, Deserialize)]
+struct IpcEnvelope {
+    #[serde(default)]
+    kind: Option<IpcKind>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(tag = "type", content = "data")]
+#[serde(tag = "type", content = "value")]
 enum InternalWindowEvent {
     Minimize,
     Maximize,
@@ -109,25 +303,77 @@ enum InternalWindowEvent {
     Move,
     IsMaximized,
     IsMinimized,
+    /// Logical inner size, replied as `{ width, height }`
+    GetSize,
+    SetSize {
+        width: f64,
+        height: f64,
+    },
+    /// Logical size including the frame, replied as `{ width, height }`
+    GetOuterSize,
+    SetOuterSize {
+        width: f64,
+        height: f64,
+    },
+    /// Smallest inner size; `null` removes the constraint
+    SetMinSize(Option<WindowSize>),
+    /// Largest inner size; `null` removes the constraint
+    SetMaxSize(Option<WindowSize>),
+    /// Logical outer position, replied as `{ x, y }`
+    GetPosition,
+    SetPosition {
+        x: f64,
+        y: f64,
+    },
+    /// Center on the monitor the window is on
+    Center,
+    /// Resize with the mouse from the given edge or corner until released
+    StartResizing(ResizeEdge),
+    SetVisible(bool),
+    IsVisible,
+    SetResizable(bool),
+    IsResizable,
+    GetTitle,
+    SetTitle(String),
+    ScaleFactor,
+    SetDecorations(bool),
+    /// Open a labeled window, replied with its label
+    OpenWindow(WindowOptions),
+    /// Close another window by label
+    CloseWindow(String),
+    /// Sent by `init.js` when the page gains or loses `beforeclose` listeners
+    GuardClose(bool),
+    /// Answer of the page's `beforeclose` listeners
+    ResolveClose(bool),
 }
 
-impl InternalWindowEvent {
-    fn as_str(&self) -> &'static str {
-        match self {
-            Self::Minimize => "Minimize",
-            Self::Maximize => "Maximize",
-            Self::UnMaximize => "UnMaximize",
-            Self::Close => "Close",
-            Self::Move => "Move",
-            Self::Focus => "Focus",
-            Self::IsFocus => "IsFocus",
-            Self::IsMaximized => "IsMaximized",
-            Self::IsMinimized => "IsMinimized",
-        }
-    }
+/// Edge or corner a frameless window is resized from, as used by
+/// `data-taocket-resize`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum ResizeEdge {
+    N,
+    Ne,
+    E,
+    Se,
+    S,
+    Sw,
+    W,
+    Nw,
+}
 
-    fn to_str_response(&self) -> String {
-        format!("{}-response", self.as_str())
+impl From<ResizeEdge> for ResizeDirection {
+    fn from(edge: ResizeEdge) -> Self {
+        match edge {
+            ResizeEdge::N => ResizeDirection::North,
+            ResizeEdge::Ne => ResizeDirection::NorthEast,
+            ResizeEdge::E => ResizeDirection::East,
+            ResizeEdge::Se => ResizeDirection::SouthEast,
+            ResizeEdge::S => ResizeDirection::South,
+            ResizeEdge::Sw => ResizeDirection::SouthWest,
+            ResizeEdge::W => ResizeDirection::West,
+            ResizeEdge::Nw => ResizeDirection::NorthWest,
+        }
     }
 }
 
@@ -135,17 +381,54 @@ impl InternalWindowEvent {
 // Builder
 // ============================================================================
 
+/// Called with the raw body and the decode error of messages no handler accepted
+pub type UnhandledHook<E> = Box<dyn Fn(&str, &IpcError, &WindowContext<E>) + Send>;
+
+/// Called on every page load with the page's handshake, compatible or not
+pub type HandshakeHook<E> = Box<dyn Fn(&Handshake, &WindowContext<E>) + Send>;
+
+/// What to do with a window the user or the app asked to close
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseAction {
+    /// Close it, after the page's `beforeclose` listeners agree
+    Close,
+    /// Hide it and keep the app running
+    Hide,
+    /// Keep it open
+    Cancel,
+}
+
+/// Called with the window label before a window closes
+pub type CloseHook = Box<dyn Fn(&str, &Window) -> CloseAction + Send>;
+
+/// Called with the window label for every forwarded native window event
+pub type WindowEventHook = Box<dyn Fn(&str, &NativeWindowEvent) + Send>;
+
 pub struct TaocketBuilder<A: AssetProvider + 'static, E: CustomEvent = (), X: CustomEvent = ()> {
     config: TaocketConfig,
     embedded_assets: Option<Arc<A>>,
     attr: WindowAttrs,
+    commands: CommandRouter<E>,
+    events: EventBus<E>,
+    middleware: Arc<MiddlewareStack<E>>,
+    binary: Arc<BinaryRouter>,
+    unhandled_hook: Option<UnhandledHook<E>>,
+    handshake_hook: Option<HandshakeHook<E>>,
+    close_hook: Option<CloseHook>,
+    window_event_hook: Option<WindowEventHook>,
+    windows: Vec<WindowOptions>,
+    runtime: Arc<tokio::runtime::Runtime>,
     _phantom: std::marker::PhantomData<E>,
     _phantom2: std::marker::PhantomData<X>,
 }
 
 impl<A: AssetProvider + 'static, E: CustomEvent, X: CustomEvent> TaocketBuilder<A, E, X> {
     pub fn new(config_path: &str, assets: Option<Arc<A>>) -> Self {
-        let taocket_config = TaocketConfig::load(config_path).unwrap();
+        Self::from_config(TaocketConfig::load(config_path).unwrap(), assets)
+    }
+
+    /// Build from an in-memory config instead of loading a file
+    pub fn from_config(taocket_config: TaocketConfig, assets: Option<Arc<A>>) -> Self {
         let attr = WindowAttrs {
             dev_url: if cfg!(debug_assertions) {
                 Some(taocket_config.dev_url.clone())
@@ -154,20 +437,198 @@ impl<A: AssetProvider + 'static, E: CustomEvent, X: CustomEvent> TaocketBuilder<
             },
             build_path: taocket_config.build_path.to_string_lossy().to_string(),
             with_devtools: taocket_config.devtools,
+            with_decorations: taocket_config.window.decorations,
+            with_websocket: true,
             websocket_port: taocket_config.websocket_port,
         };
         Self {
             //  pub embedded_assets: Option<Arc<dyn AssetProvider>>,
             embedded_assets: assets,
             config: taocket_config,
-            attr: attr,
+            attr,
+            commands: CommandRouter::new(),
+            events: EventBus::new(),
+            middleware: Arc::new(MiddlewareStack::new()),
+            binary: Arc::new(BinaryRouter::new()),
+            unhandled_hook: None,
+            handshake_hook: None,
+            close_hook: None,
+            window_event_hook: None,
+            windows: Vec::new(),
+            runtime: Arc::new(
+                tokio::runtime::Builder::new_multi_thread()
+                    .thread_name("taocket-runtime")
+                    .enable_all()
+                    .build()
+                    .expect("Failed to build tokio runtime"),
+            ),
             _phantom: std::marker::PhantomData,
             _phantom2: std::marker::PhantomData,
         }
     }
 
+    /// Load the page from `url` instead of serving the frontend assets; by
+    /// default debug builds use the config's `dev_url`
+    pub fn dev_url(mut self, url: Option<String>) -> Self {
+        self.attr.dev_url = url;
+        self
+    }
+
+    /// Show the native title bar and borders; defaults to the config's
+    /// `window.decorations`
+    pub fn decorations(mut self, enabled: bool) -> Self {
+        self.attr.with_decorations = enabled;
+        self
+    }
+
+    /// Start the WebSocket server on the config's `websocket_port`; on by
+    /// default
+    pub fn websocket(mut self, enabled: bool) -> Self {
+        self.attr.with_websocket = enabled;
+        self
+    }
+
+    /// Decide whether a window may close; runs for native close requests,
+    /// the `Close` built-in and `close_window` alike
+    pub fn on_close<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&str, &Window) -> CloseAction + Send + 'static,
+    {
+        self.close_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Observe native window events (resize, move, focus, theme, ...); they
+    /// are also emitted to the page, see `taocket.window.onEvent`
+    pub fn on_window_event<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&str, &NativeWindowEvent) + Send + 'static,
+    {
+        self.window_event_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Open a labeled secondary window next to the main one on startup
+    pub fn window(mut self, options: WindowOptions) -> Self {
+        self.windows.push(options);
+        self
+    }
+
+    /// Register a named IPC command, invoked from JS with `invoke(name, args)`
+    pub fn command<Args, R, Er, C>(mut self, name: &str, handler: C) -> Self
+    where
+        Args: DeserializeOwned,
+        R: Serialize,
+        Er: std::fmt::Display,
+        C: Fn(Args, WindowContext<E>) -> Result<R, Er> + Send + 'static,
+    {
+        self.commands.register(name, handler);
+        self
+    }
+
+    /// Register a named IPC command that runs on the builder's tokio runtime,
+    /// so slow work doesn't block the UI thread
+    pub fn async_command<Args, R, Er, C, Fut>(mut self, name: &str, handler: C) -> Self
+    where
+        Args: DeserializeOwned,
+        R: Serialize,
+        Er: std::fmt::Display,
+        C: Fn(Args, AsyncContext<E>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<R, Er>> + Send + 'static,
+    {
+        self.commands.register_async(name, handler);
+        self
+    }
+
+    /// Register a binary command, reached from JS with
+    /// `taocket.binary(name, body)`; it receives the raw bytes and headers and
+    /// runs on the builder's tokio runtime. Bodies are capped by the config's
+    /// `limits.max_binary_message_size`; binary calls carry no call id and
+    /// bypass `middleware`. Only pages on the app's origin may call them
+    pub fn binary_command<C, Fut, Er>(mut self, name: &str, handler: C) -> Self
+    where
+        C: Fn(BinaryRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<BinaryResponse, Er>> + Send + 'static,
+        Er: std::fmt::Display,
+    {
+        Arc::get_mut(&mut self.binary)
+            .expect("binary commands are only shared once the app runs")
+            .register(name, handler);
+        self
+    }
+
+    /// Listen for a topic emitted from JS with `taocket.emit(topic, payload)`
+    pub fn listen<T, L>(self, topic: &str, listener: L) -> Self
+    where
+        T: DeserializeOwned,
+        L: Fn(T, WindowContext<E>) + Send + 'static,
+    {
+        self.events.listen(topic, listener);
+        self
+    }
+
+    /// Add a layer that runs around every IPC call, in registration order
+    pub fn middleware<M: Middleware<E>>(mut self, middleware: M) -> Self {
+        Arc::get_mut(&mut self.middleware)
+            .expect("middleware stack is only shared once the app runs")
+            .push(middleware);
+        self
+    }
+
+    /// Allow the calls listed in `capability`, on top of the config's
+    /// `capabilities`; once any exist, unlisted calls are rejected except
+    /// the close protocol's `INTERNAL_WINDOW_COMMANDS`. Drag regions need
+    /// `Capability::drag_regions`
+    pub fn capability(mut self, capability: Capability) -> Self {
+        self.config.capabilities.push(capability);
+        self
+    }
+
+    /// Hook for IPC messages that match no built-in, command or event variant;
+    /// the caller is still rejected with the structured error
+    pub fn on_unhandled_message<U>(mut self, hook: U) -> Self
+    where
+        U: Fn(&str, &IpcError, &WindowContext<E>) + Send + 'static,
+    {
+        self.unhandled_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Hook run when a page announces its protocol version; mismatched pages
+    /// are also rejected and shown an error banner
+    pub fn on_handshake<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&Handshake, &WindowContext<E>) + Send + 'static,
+    {
+        self.handshake_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Write TypeScript bindings for the protocol types and `E`/`X` into the
+    /// config's `bindings_dir`; only runs in debug builds
+    pub fn export_bindings(self) -> Self
+    where
+        E: TS + 'static,
+        X: TS + 'static,
+    {
+        if cfg!(debug_assertions)
+            && let Some(dir) = &self.config.bindings_dir
+            && let Err(e) = taocket_bindings::export_bindings::<E, X>(dir)
+        {
+            log::error!("Failed to export TypeScript bindings: {}", e);
+        }
+        self
+    }
+
+    /// Handle to the runtime async commands are spawned on
+    pub fn runtime(&self) -> tokio::runtime::Handle {
+        self.runtime.handle().clone()
+    }
+
+    /// Open the windows and run the event loop; returns once the main window
+    /// has closed
     pub fn run<F, S, W, H>(
-        self,
+        mut self,
         init_window: S,
         handler: F,
         ws_handler: W,
@@ -183,7 +644,12 @@ impl<A: AssetProvider + 'static, E: CustomEvent, X: CustomEvent> TaocketBuilder<
     {
         let event_loop = EventLoopBuilder::<E>::with_user_event().build();
         let proxy = event_loop.create_proxy();
-        let window = self.create_window(&event_loop)?;
+        let main_options = self.main_window_options();
+        let mut registry = WindowRegistry::default();
+        if self.config.persist_geometry {
+            registry.track_geometry(GeometryTracker::load(self.config.state_path()));
+        }
+        let window = self.create_window(&event_loop, &main_options, &registry)?;
         let hotkey_manager =
             TaocketHotkeyManager::new().expect("Failed to initialize hotkey manager");
         let manager = Arc::new(Mutex::new(hotkey_manager));
@@ -191,106 +657,311 @@ impl<A: AssetProvider + 'static, E: CustomEvent, X: CustomEvent> TaocketBuilder<
         let manager_clone_eventloop = Arc::clone(&manager);
         init_window(&window, manager_clone, self.config.clone());
         let websocket_clients = Arc::new(Mutex::new(HashMap::new()));
-        let webview_holder = self.create_webview(&window, &websocket_clients, &proxy, handler)?;
+        let (loop_tx, loop_rx) = crossbeam_channel::unbounded::<LoopCommand>();
+        let shared = Rc::new(IpcShared {
+            clients: Arc::clone(&websocket_clients),
+            proxy: proxy.clone(),
+            loop_tx,
+            commands: std::mem::take(&mut self.commands),
+            events: std::mem::take(&mut self.events),
+            capabilities: self.config.capabilities.clone(),
+            limits: self.config.limits,
+            middleware: Arc::clone(&self.middleware),
+            unhandled_hook: self.unhandled_hook.take(),
+            handshake_hook: self.handshake_hook.take(),
+        });
+        let handler = Rc::new(handler);
+        self.attach_webview(window, &main_options, &shared, &handler, &mut registry)?;
+        for options in std::mem::take(&mut self.windows) {
+            let label = options.label.clone();
+            if let Err(e) = self.open_window(&event_loop, options, &shared, &handler, &mut registry)
+            {
+                log::error!("Failed to open window '{}': {}", label, e);
+            }
+        }
 
-        self.spawn_websocket_thread(websocket_clients, ws_handler, &proxy);
+        if self.attr.with_websocket
+            && let Err(e) = self.spawn_websocket_thread(websocket_clients, ws_handler, &proxy)
+        {
+            log::error!(
+                "Failed to start WebSocket server on port {}: {:?}",
+                self.attr.websocket_port,
+                e
+            );
+        }
         self.run_event_loop(
             event_loop,
-            window,
-            webview_holder,
+            registry,
+            shared,
+            handler,
+            loop_rx,
             manager_clone_eventloop,
             hotkey_handler,
         )
     }
 
+    /// Options of the main window, taken from the config
+    fn main_window_options(&self) -> WindowOptions {
+        let mut options = WindowOptions::new(MAIN_WINDOW)
+            .size(self.config.size.width, self.config.size.height)
+            .decorations(self.attr.with_decorations)
+            .resizable(self.config.window.resizable)
+            .top_most(self.config.top_most)
+            .devtools(self.config.devtools);
+        options.title = self.config.window.title.clone();
+        options
+    }
+
+    /// Apply the `[window]` attributes of the config that are not part of
+    /// `WindowOptions`
+    fn apply_window_config(&self, mut builder: WindowBuilder, is_main: bool) -> WindowBuilder {
+        let config = &self.config.window;
+        builder = builder.with_transparent(config.transparent);
+        if let Some(path) = &config.icon {
+            match load_icon(path) {
+                Ok(icon) => builder = builder.with_window_icon(Some(icon)),
+                Err(e) => log::warn!("Failed to load window icon {:?}: {}", path, e),
+            }
+        }
+        if !is_main {
+            return builder;
+        }
+
+        builder = builder
+            .with_visible(config.visible)
+            .with_maximized(config.maximized);
+        if let Some(size) = &config.min_size {
+            builder = builder.with_min_inner_size(LogicalSize::new(size.width, size.height));
+        }
+        if let Some(size) = &config.max_size {
+            builder = builder.with_max_inner_size(LogicalSize::new(size.width, size.height));
+        }
+        if let Some(position) = config.position {
+            builder = builder.with_position(LogicalPosition::new(position.x, position.y));
+        }
+        #[cfg(target_os = "windows")]
+        {
+            use tao::platform::windows::WindowBuilderExtWindows;
+            builder = builder.with_skip_taskbar(config.skip_taskbar);
+        }
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        {
+            use tao::platform::unix::WindowBuilderExtUnix;
+            builder = builder.with_skip_taskbar(config.skip_taskbar);
+        }
+        builder
+    }
+
+    /// Page the main window loads; relative window URLs resolve against it
+    fn start_url(&self) -> String {
+        match &self.attr.dev_url {
+            Some(dev_url) => dev_url.clone(),
+            None => format!("{}://localhost", PROTOCOL_NAME),
+        }
+    }
+
+    /// Build the window for `options`, restoring its saved geometry if any
     fn create_window(
         &self,
-        event_loop: &tao::event_loop::EventLoop<E>,
+        target: &EventLoopWindowTarget<E>,
+        options: &WindowOptions,
+        registry: &WindowRegistry,
     ) -> wry::Result<Arc<Window>> {
-        let window = WindowBuilder::new()
-            .with_transparent(true)
-            .with_inner_size(LogicalSize::new(
-                self.config.size.width,
-                self.config.size.height,
-            ))
-            .with_always_on_top(self.config.top_most)
-            .build(event_loop)
-            .expect("Failed to create window");
+        let builder = self.apply_window_config(WindowBuilder::new(), options.label == MAIN_WINDOW);
+        let mut builder = builder
+            .with_always_on_top(options.top_most)
+            .with_decorations(options.decorations)
+            .with_resizable(options.resizable);
+        if let (Some(width), Some(height)) = (options.width, options.height) {
+            builder = builder.with_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some(title) = &options.title {
+            builder = builder.with_title(title);
+        }
+        if let Some(geometry) = registry.saved_geometry(&options.label) {
+            let monitors: Vec<MonitorRect> = target
+                .available_monitors()
+                .map(|m| MonitorRect::from(&m))
+                .collect();
+            builder = geometry.apply(builder, &monitors);
+        }
+        let window = builder
+            .build(target)
+            .map_err(|e| wry::Error::Io(std::io::Error::other(e)))?;
 
         Ok(Arc::new(window))
     }
 
-    fn create_webview<F>(
+    /// Create a window for `options` and its webview
+    fn open_window<F>(
         &self,
-        window: &Arc<Window>,
-        websocket_clients: &Clients,
-        proxy: &EventLoopProxy<E>,
-        handler: F,
-    ) -> wry::Result<WebviewContext>
+        target: &EventLoopWindowTarget<E>,
+        options: WindowOptions,
+        shared: &Rc<IpcShared<E>>,
+        handler: &Rc<F>,
+        registry: &mut WindowRegistry,
+    ) -> Result<(), String>
     where
         E: DeserializeOwned + Serialize,
-        F: Fn(Payload<E>, WindowContext<E>) + Send + 'static,
+        F: Fn(Payload<E>, WindowContext<E>) + 'static,
     {
-        let window_clone = Arc::clone(window);
-        let clients_clone = Arc::clone(websocket_clients);
-        let proxy_clone = Arc::new(proxy.clone());
+        if options.label.is_empty() {
+            return Err("Window label cannot be empty".to_string());
+        }
+        if registry.contains(&options.label) {
+            return Err(format!("Window '{}' is already open", options.label));
+        }
+        let window = self
+            .create_window(target, &options, registry)
+            .map_err(|e| e.to_string())?;
+        self.attach_webview(window, &options, shared, handler, registry)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Build the webview of a labeled window and register both
+    fn attach_webview<F>(
+        &self,
+        window: Arc<Window>,
+        options: &WindowOptions,
+        shared: &Rc<IpcShared<E>>,
+        handler: &Rc<F>,
+        registry: &mut WindowRegistry,
+    ) -> wry::Result<()>
+    where
+        E: DeserializeOwned + Serialize,
+        F: Fn(Payload<E>, WindowContext<E>) + 'static,
+    {
+        let cancels = CancelRegistry::default();
+        // The webview never leaves the main thread; `WebviewContext` is an Arc for API reasons
+        #[allow(clippy::arc_with_non_send_sync)]
         let webview_holder: WebviewContext = Arc::new(Mutex::new(None));
-        let webview_clone = Arc::clone(&webview_holder);
+        let ipc_state = IpcState {
+            shared: Rc::clone(shared),
+            window: Arc::clone(&window),
+            webview: Arc::clone(&webview_holder),
+            loop_handle: LoopHandle {
+                label: options.label.clone(),
+                loop_tx: shared.loop_tx.clone(),
+                runtime: self.runtime(),
+                cancels: cancels.clone(),
+            },
+        };
+        let handler = Rc::clone(handler);
+        let start_url = self.start_url();
+        let url = options.resolve_url(&start_url);
 
         let webview_builder = WebViewBuilder::new()
-            .with_devtools(self.config.devtools)
-            .with_initialization_script(include_str!("scripts/init.js"))
-            .with_initialization_script(include_str!("scripts/dragevent.js"))
-            .with_new_window_req_handler(Self::handle_new_window_request)
-            .with_ipc_handler(move |req: Request<String>| {
-                Self::handle_ipc_message(
-                    req,
-                    &window_clone,
-                    &clients_clone,
-                    &webview_clone,
-                    &proxy_clone,
-                    &handler,
-                );
-            });
-        let dev_url = self.attr.dev_url.as_deref().unwrap_or("");
-        let webview_builder = if cfg!(debug_assertions) {
+            .with_devtools(options.devtools.unwrap_or(self.config.devtools))
+            .with_new_window_req_handler(Self::handle_new_window_request);
+        let webview_builder = if is_app_origin(&url, &start_url) {
+            webview_builder
+                .with_initialization_script(window_script(
+                    &options.label,
+                    self.config.window.resize_border,
+                    self.config.window.double_click,
+                ))
+                .with_initialization_script(resize_border_script(&window))
+                .with_initialization_script(init_script())
+                .with_initialization_script(include_str!("scripts/dragevent.js"))
+                .with_ipc_handler(move |req: Request<String>| {
+                    Self::handle_ipc_message(req, &ipc_state, handler.as_ref());
+                })
+        } else {
+            log::warn!(
+                "Window '{}' loads '{}' outside the app; it gets no IPC bridge",
+                options.label,
+                url
+            );
             webview_builder
-                .with_url(dev_url)
-                .with_on_page_load_handler(|p, s| println!("loading page {s}"))
+        };
+        // Registered in dev mode too so binary commands work against the dev server
+        let webview_builder =
+            self.setup_custom_protocol(webview_builder, is_app_origin(&url, &start_url));
+        let webview_builder = if self.attr.dev_url.is_some() {
+            webview_builder
+                .with_url(url)
+                .with_on_page_load_handler(|_, s| log::debug!("Loading page {s}"))
         } else {
-            self.setup_production_protocol(webview_builder)
+            webview_builder.with_url(url)
         };
 
-        let webview = webview_builder.build(window)?;
+        let webview = webview_builder.build(window.as_ref())?;
         *webview_holder.lock() = Some(webview);
-        Ok(webview_holder)
+        registry.insert(
+            options.label.clone(),
+            WindowEntry {
+                window,
+                webview: webview_holder,
+                cancels,
+                close_guard: false,
+                closing: false,
+            },
+        );
+        Ok(())
     }
 
-    fn setup_production_protocol<'a>(&self, builder: WebViewBuilder<'a>) -> WebViewBuilder<'a> {
-        let protocol_name = "taocket";
+    /// Serve the binary command route to pages on the app's origin and,
+    /// without a dev server, the frontend assets
+    fn setup_custom_protocol<'a>(
+        &self,
+        builder: WebViewBuilder<'a>,
+        serve_binary: bool,
+    ) -> WebViewBuilder<'a> {
+        let serve_assets = self.attr.dev_url.is_none();
         let emmbeded_assets = self.embedded_assets.as_ref().map(Arc::clone);
         let build_path = self.attr.build_path.clone();
 
-        let build_path = if emmbeded_assets.is_none() {
+        let build_path = if serve_assets && emmbeded_assets.is_none() {
             Some(taocket_utils::resolve_frontend_path(build_path))
         } else {
             None
         };
+        let binary = Arc::clone(&self.binary);
+        let capabilities = self.config.capabilities.clone();
+        let limits = self.config.limits;
+        let runtime = self.runtime();
+        let app_origin = self.start_url();
+
+        builder.with_asynchronous_custom_protocol(
+            PROTOCOL_NAME.to_string(),
+            move |_webview_id, request, responder| {
+                if taocket_binary::is_binary_route(request.uri().path()) {
+                    if !serve_binary {
+                        let path = request.uri().path();
+                        log::warn!("Denied binary request {} from a page outside the app", path);
+                        let response = Self::not_found_response(path)
+                            .or_else(|_| Self::error_response("Internal server error"));
+                        return responder.respond(response.unwrap());
+                    }
+                    match binary.handle(request, &app_origin, &capabilities, &limits) {
+                        BinaryReply::Ready(response) => responder.respond(response),
+                        BinaryReply::Pending(pending) => {
+                            runtime.spawn(async move { responder.respond(pending.await) });
+                        }
+                    }
+                    return;
+                }
 
-        builder
-            .with_custom_protocol(protocol_name.to_string(), move |_webview_id, request| {
-                match Self::handle_asset_request(request, &emmbeded_assets, &build_path) {
-                    Ok(response) => response.map(Into::into),
+                let response = if serve_assets {
+                    Self::handle_asset_request(request, &emmbeded_assets, &build_path)
+                } else {
+                    Self::not_found_response(request.uri().path())
+                };
+                match response {
+                    Ok(response) => responder.respond(response),
                     Err(e) => {
-                        eprintln!("Asset request error: {}", e);
-                        Self::error_response("Internal server error")
-                            .unwrap()
-                            .map(Into::into)
+                        log::error!("Asset request error: {}", e);
+                        responder.respond(Self::error_response("Internal server error").unwrap())
                     }
                 }
-            })
-            .with_new_window_req_handler(Self::handle_new_window_request)
-            .with_url(&format!("{}://localhost", protocol_name))
+            },
+        )
     }
 
     fn handle_asset_request(
@@ -302,10 +973,10 @@ impl<A: AssetProvider + 'static, E: CustomEvent, X: CustomEvent> TaocketBuilder<
         let path = if path.is_empty() { "index.html" } else { path };
 
         // Try embedded assets first
-        if let Some(assets) = embedded_assets {
-            if let Some(content) = assets.get(path) {
-                return Self::create_response(path, content);
-            }
+        if let Some(assets) = embedded_assets
+            && let Some(content) = assets.get(path)
+        {
+            return Self::create_response(path, content);
         }
 
         // Fallback to filesystem
@@ -345,127 +1016,596 @@ impl<A: AssetProvider + 'static, E: CustomEvent, X: CustomEvent> TaocketBuilder<
 
     fn handle_new_window_request(url: String, _: NewWindowFeatures) -> NewWindowResponse {
         if let Err(e) = open::that(&url) {
-            eprintln!("Failed to open URL: {}", e);
+            log::warn!("Failed to open URL: {}", e);
         }
         NewWindowResponse::Deny
     }
 
-    fn handle_ipc_message<F>(
-        req: Request<String>,
-        window: &Arc<Window>,
-        clients: &Clients,
-        webview_holder: &WebviewContext,
-        proxy: &Arc<EventLoopProxy<E>>,
+    fn handle_ipc_message<F>(req: Request<String>, state: &IpcState<E>, handler: &F)
+    where
+        E: DeserializeOwned + Serialize,
+        F: Fn(Payload<E>, WindowContext<E>),
+    {
+        let url = req.uri().to_string();
+        if let Err(error) = state.shared.limits.check_ipc(req.body()) {
+            return Self::reject_oversized(req.body(), error, state);
+        }
+        Self::dispatch_message(req.body(), &url, state.context(), state, handler);
+    }
+
+    /// Reply to a message over the limits, and to every call of a batch,
+    /// without decoding more than their ids
+    fn reject_oversized(body: &str, error: IpcError, state: &IpcState<E>) {
+        log::warn!("Rejected IPC message: {}", error);
+        let mut responses = member_rejections(body, &error);
+        if let Ok(MessageId { payload }) = serde_json::from_str::<MessageId>(body) {
+            responses.push(IpcResponse::err(payload.id, error));
+        }
+        if !responses.is_empty()
+            && let Err(e) = state.context().respond_all(&responses)
+        {
+            log::error!("Failed to reject oversized message: {}", e);
+        }
+    }
+
+    fn dispatch_message<F>(
+        body: &str,
+        url: &str,
+        context: WindowContext<E>,
+        state: &IpcState<E>,
         handler: &F,
     ) where
         E: DeserializeOwned + Serialize,
         F: Fn(Payload<E>, WindowContext<E>),
     {
-        let body = req.body();
+        let envelope = match taocket_ipc::decode_str::<IpcEnvelope>(body) {
+            Ok(envelope) => envelope,
+            Err(error) => return Self::reject_unhandled(body, error, state, &context),
+        };
+
+        let (id, name) = call_target(body);
+        let legacy = envelope.kind.is_none();
+        let kind = message_kind(envelope.kind, name.as_deref());
+
+        // Each call of a batch goes through middleware and the allowlist on its own
+        if kind == IpcKind::Batch {
+            if context.batch().is_some() {
+                let error = IpcError::with_kind(
+                    taocket_ipc::IpcErrorKind::InvalidMessage,
+                    "Batches cannot be nested",
+                );
+                return Self::reject_unhandled(body, error, state, &context);
+            }
+            return Self::handle_batch(body, url, state, handler);
+        }
+
+        // Cancels only affect calls the page already made, so they skip the allowlist
+        if kind == IpcKind::Cancel {
+            if let Some(id) = id {
+                state.loop_handle.cancels.cancel(id);
+                state.shared.middleware.abandon(state.label(), id);
+            }
+            return;
+        }
+
+        if kind == IpcKind::Handshake {
+            return Self::handle_handshake(body, id, state, &context);
+        }
+
+        let request = IpcRequest {
+            kind,
+            id,
+            event: name.clone(),
+            url: url.to_string(),
+            window: state.label().to_string(),
+            body: body.to_string(),
+        };
+        let is_command = name
+            .as_deref()
+            .is_some_and(|name| state.shared.commands.contains(name));
+        let track = always_settles(kind, is_command);
+        if let Err(error) = state.shared.middleware.begin(request, &context, track) {
+            if let Some(id) = id
+                && let Err(e) = context.reject(id, error)
+            {
+                log::error!("Failed to reject call stopped by middleware: {}", e);
+            }
+            return;
+        }
+
+        if !state.shared.capabilities.is_empty() {
+            let name = name.as_deref().unwrap_or_default();
+            let access = match kind {
+                IpcKind::Window => Access::Window(name),
+                IpcKind::Emit => Access::Topic(name),
+                IpcKind::Invoke | IpcKind::Cancel | IpcKind::Handshake | IpcKind::Batch => {
+                    Access::Command(name)
+                }
+            };
+            if let Err(error) = taocket_capability::check(&state.shared.capabilities, url, access) {
+                log::warn!("Denied IPC call: {}", error);
+                if let Some(id) = id
+                    && let Err(e) = context.reject(id, error)
+                {
+                    log::error!("Failed to reject denied call: {}", e);
+                }
+                return;
+            }
+        }
+
+        // Handle built-in window commands
+        if kind == IpcKind::Window {
+            if let Some(name) = &name
+                && let Err(error) = taocket_ipc::check_event::<InternalWindowEvent>(name)
+            {
+                return Self::reject_unhandled(body, error, state, &context);
+            }
+            match decode_window_message(body, legacy) {
+                Ok(msg) if legacy => handle_legacy_window_event(msg.payload, &context),
+                Ok(msg) => handle_internal_window_event(msg.payload, &context),
+                Err(error) => Self::reject_unhandled(body, error, state, &context),
+            }
+            return;
+        }
+
+        // Handle topic events emitted from JS; `event.type` is the topic
+        if kind == IpcKind::Emit {
+            match taocket_ipc::decode_str::<IpcMessage<CommandCall>>(body) {
+                Ok(msg) => {
+                    let id = msg.payload.id;
+                    let event = msg.payload.event;
+                    let response =
+                        match state
+                            .shared
+                            .events
+                            .dispatch(&event.name, event.value, &context)
+                        {
+                            Ok(()) => IpcResponse::ok(id, serde_json::Value::Null),
+                            Err(e) => IpcResponse::err(id, e),
+                        };
+                    if let Err(e) = context.respond(response) {
+                        log::error!("Failed to acknowledge emitted event: {}", e);
+                    }
+                }
+                Err(error) => Self::reject_unhandled(body, error, state, &context),
+            }
+            return;
+        }
 
-        // Handle internal window events
-        if let Ok(msg) = serde_json::from_str::<IpcMessage<InternalWindowEvent>>(body) {
-            if let Some(ref webview) = *webview_holder.lock() {
-                handle_internal_window_event(msg.payload, window, webview);
+        // Handle named commands registered on the builder
+        if let Ok(msg) = serde_json::from_str::<IpcMessage<CommandCall>>(body)
+            && state.shared.commands.contains(&msg.payload.event.name)
+        {
+            let id = msg.payload.id;
+            match state
+                .shared
+                .commands
+                .dispatch(id, msg.payload.event, context.clone())
+            {
+                Some(Dispatched::Ready(result)) => {
+                    let response = match result {
+                        Ok(value) => IpcResponse::ok(id, value),
+                        Err(e) => IpcResponse::err(id, e),
+                    };
+                    if let Err(e) = context.respond(response) {
+                        log::error!("Failed to reply to command: {}", e);
+                    }
+                }
+                Some(Dispatched::Pending(task)) => {
+                    let task = match context.batch() {
+                        Some(batch) => batch.defer(id, task),
+                        None => Some(task),
+                    };
+                    if let Some(task) = task {
+                        state.loop_handle.spawn_response(id, task);
+                    }
+                }
+                None => {}
             }
             return;
         }
 
-        // Handle custom user events
-        if let Ok(msg) = serde_json::from_str::<IpcMessage<E>>(body) {
-            let context = WindowContext::with_proxy(
-                Arc::clone(window),
-                Arc::clone(webview_holder),
-                Arc::clone(clients),
-                Arc::clone(proxy),
+        // Handle custom user events; a value that doesn't fit `E` rejects the
+        // caller with a typed `InvalidPayload` error
+        if let Some(name) = &name
+            && let Err(error) = taocket_ipc::check_event::<E>(name)
+        {
+            return Self::reject_unhandled(body, error, state, &context);
+        }
+        match decode_user_message::<E>(body) {
+            Ok(msg) => handler(msg.payload, context),
+            Err(error) => Self::reject_unhandled(body, error, state, &context),
+        }
+    }
+
+    /// Dispatch every call of a batch in order, then send the sync replies in
+    /// one script and the async ones together once they have all finished
+    fn handle_batch<F>(body: &str, url: &str, state: &IpcState<E>, handler: &F)
+    where
+        E: DeserializeOwned + Serialize,
+        F: Fn(Payload<E>, WindowContext<E>),
+    {
+        let calls = match taocket_ipc::decode_str::<IpcMessage<BatchCalls>>(body) {
+            Ok(msg) => msg.payload.event.value,
+            Err(error) => {
+                // The frontend waits on the calls, not on the batch itself
+                let responses = member_rejections(body, &error);
+                if !responses.is_empty()
+                    && let Err(e) = state.context().respond_all(&responses)
+                {
+                    log::error!("Failed to reject batch: {}", e);
+                }
+                return Self::reject_unhandled(body, error, state, &state.context());
+            }
+        };
+
+        let batch = Arc::new(ReplyBatch::default());
+        for call in &calls {
+            let context = state.context().with_batch(Arc::clone(&batch));
+            Self::dispatch_message(call.get(), url, context, state, handler);
+        }
+
+        let (responses, deferred) = batch.close();
+        if !responses.is_empty()
+            && let Err(e) = state.context().respond_all(&responses)
+        {
+            log::error!("Failed to reply to batch: {}", e);
+        }
+        if !deferred.is_empty() {
+            state.loop_handle.spawn_batch(deferred);
+        }
+    }
+
+    /// Check the page's protocol version, run the lifecycle hook and reply
+    /// with the backend version or a mismatch error
+    fn handle_handshake(
+        body: &str,
+        id: Option<i32>,
+        state: &IpcState<E>,
+        context: &WindowContext<E>,
+    ) {
+        let Some(handshake) = Handshake::from_body(body) else {
+            let error = IpcError::with_kind(
+                taocket_ipc::IpcErrorKind::ProtocolMismatch,
+                "Unreadable handshake; the page's taocket script is incompatible",
             );
-            handler(msg.payload, context);
+            return Self::reject_unhandled(body, error, state, context);
+        };
+
+        if let Some(hook) = &state.shared.handshake_hook {
+            hook(&handshake, context);
+        }
+        // A freshly loaded page has no `beforeclose` listeners yet
+        let _ = state
+            .loop_handle
+            .send(LoopCommand::CloseGuard(state.label().to_string(), false));
+        let Some(id) = id else {
+            return;
+        };
+
+        let response = handshake.reply(id);
+        match &response.result {
+            IpcResult::Ok(_) => log::debug!(
+                "Handshake from {} (protocol v{})",
+                handshake.url,
+                handshake.version
+            ),
+            IpcResult::Err(error) => log::error!("{} ({})", error.message, handshake.url),
+        }
+        if let Err(e) = context.respond(response) {
+            log::error!("Failed to answer handshake: {}", e);
         }
     }
 
-    fn run_event_loop<H>(
+    /// Log an undecodable or unknown message, notify the user hook and reject
+    /// the caller's promise if an id can be recovered from the body
+    fn reject_unhandled(
+        body: &str,
+        mut error: IpcError,
+        state: &IpcState<E>,
+        context: &WindowContext<E>,
+    ) {
+        let (id, event) = call_target(body);
+        if let Some(event) = event {
+            error = error.event(event);
+        }
+
+        log::warn!("Unhandled IPC message: {}", error);
+
+        if let Some(hook) = &state.shared.unhandled_hook {
+            hook(body, &error, context);
+        }
+
+        if let Some(id) = id
+            && let Err(e) = context.respond(IpcResponse::err(id, error))
+        {
+            log::error!("Failed to reject unhandled message: {}", e);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    /// Run until the main window closes, then save window state and close
+    /// WebSocket clients before returning
+    fn run_event_loop<F, H>(
         self,
-        event_loop: tao::event_loop::EventLoop<E>,
-        window: Arc<Window>,
-        webview_holder: WebviewContext,
+        mut event_loop: tao::event_loop::EventLoop<E>,
+        mut registry: WindowRegistry,
+        shared: Rc<IpcShared<E>>,
+        handler: Rc<F>,
+        loop_rx: Receiver<LoopCommand>,
         hotkeymanager: Arc<Mutex<TaocketHotkeyManager>>,
         hotkey_handler: H,
     ) -> wry::Result<()>
     where
-        E: Serialize,
+        E: DeserializeOwned + Serialize,
         X: Serialize + std::fmt::Debug,
+        F: Fn(Payload<E>, WindowContext<E>) + 'static,
         H: Fn(Dispatcher<X>, &HotkeyAndFunc) + Send + 'static,
     {
         let receiver = global_hotkey::GlobalHotKeyEvent::receiver();
         let (tx, rx) = crossbeam_channel::unbounded::<TxEvent<X>>();
         let dispatcher = Dispatcher::new(tx);
-        event_loop.run(move |event, _, control_flow| {
-            // *control_flow = ControlFlow::Wait;
-            *control_flow = ControlFlow::WaitUntil(std::time::Instant::now() + Duration::from_millis(16));
+        event_loop.run_return(|event, target, control_flow| {
+            *control_flow =
+                ControlFlow::WaitUntil(std::time::Instant::now() + Duration::from_millis(16));
 
             if let Ok(event) = receiver.try_recv() {
+                let guard = hotkeymanager.lock();
+                for (key, hk) in guard.registered_hotkeys.iter() {
+                    if key == &event.id && event.state == Released {
+                        hotkey_handler(dispatcher.clone(), hk);
+                    }
+                }
+            }
 
-			let guard = hotkeymanager.lock();
-			for(key,hk) in	guard.registered_hotkeys.iter(){
-				if key == &event.id && event.state == Released{
-					hotkey_handler(dispatcher.clone(),hk);
-				}
-			}
-                  }
             match event {
                 Event::MainEventsCleared => {
-                    window.request_redraw();
+                    if let Some(main) = registry.get(MAIN_WINDOW) {
+                        main.window.request_redraw();
+                    }
                 }
                 Event::UserEvent(custom_event) => {
-                    if let Some(ref webview) = *webview_holder.lock() {
-                        if let Ok(json) = serde_json::to_string(&custom_event) {
-
-
-                            let script = format!(
-                                "window.dispatchEvent(new CustomEvent('taocket:websocket|event', {{ detail: {} }}));",
-                                json
-                            );
-
-                            if let Err(e) = webview.evaluate_script(&script) {
-                                eprintln!("Failed to send event to frontend: {}", e);
+                    if let Ok(json) = serde_json::to_string(&custom_event) {
+                        let script = dispatch_event_script(USER_EVENT_NAME, &json);
+                        for entry in registry.targets(&Target::All) {
+                            if let Some(ref webview) = *entry.webview.lock()
+                                && let Err(e) = webview.evaluate_script(&script)
+                            {
+                                log::error!("Failed to send event to frontend: {}", e);
                             }
                         }
                     }
                 }
-                Event::WindowEvent { event, .. } => match event {
-                    tao::event::WindowEvent::CloseRequested => {
-                        *control_flow = ControlFlow::Exit;
+                Event::WindowEvent {
+                    window_id, event, ..
+                } => {
+                    if let Some(label) = registry.label_of(window_id).map(str::to_string) {
+                        match event {
+                            tao::event::WindowEvent::Moved(_)
+                            | tao::event::WindowEvent::Resized(_) => {
+                                registry.update_geometry(window_id)
+                            }
+                            tao::event::WindowEvent::CloseRequested => {
+                                self.request_close(&label, &mut registry, control_flow)
+                            }
+                            _ => {}
+                        }
+                        // Maximizing resizes the window, so this catches it
+                        // too; focus covers changes made from Rust
+                        if matches!(
+                            event,
+                            tao::event::WindowEvent::Resized(_)
+                                | tao::event::WindowEvent::Focused(true)
+                        ) && let Some(entry) = registry.get(&label)
+                        {
+                            push_resize_border(&entry.window, &entry.webview);
+                        }
+                        let now = std::time::Instant::now();
+                        if let Some(native) = registry.translate_event(&label, &event, now) {
+                            self.forward_window_event(&registry, &label, &native);
+                        }
                     }
-                    _ => {}
-                },
+                }
                 _ => {}
             }
-            while let Ok(msg) = rx.try_recv(){
-            match msg{
-                TxEvent::User(_) => {
-                            },
-                TxEvent::Window(w) => {
-               	            match w {
-                                UserWindowEvent::Minimize => {
-                                window.set_minimized(true);
-                                },
-                                UserWindowEvent::Maximize => window.set_maximized(true),
-                                UserWindowEvent::UnMaximize =>window.set_maximized(false) ,
-                                UserWindowEvent::Close => *control_flow=ControlFlow::Exit,
-                                UserWindowEvent::Focus => window.set_focus(),
-                            }
-                            },
-                TxEvent::Script(scrpt) => {
-
-		                        if let Some(ref webview) = *webview_holder.lock() {
-		                         println!("message");
-		                         _ = webview.evaluate_script(&scrpt);
-		                        }
-                },
-            };
 
+            while let Ok(msg) = rx.try_recv() {
+                let command = match msg {
+                    TxEvent::User(event) => match serde_json::to_string(&event) {
+                        Ok(json) => LoopCommand::Script(
+                            Target::All,
+                            dispatch_event_script(DISPATCH_EVENT_NAME, &json),
+                        ),
+                        Err(e) => {
+                            log::error!("Failed to serialize dispatched event: {}", e);
+                            continue;
+                        }
+                    },
+                    TxEvent::Window(w) => LoopCommand::Window(MAIN_WINDOW.to_string(), w),
+                    TxEvent::Script(script) => LoopCommand::Script(Target::All, script),
+                };
+                self.handle_loop_command(
+                    command,
+                    target,
+                    &mut registry,
+                    &shared,
+                    &handler,
+                    control_flow,
+                );
+            }
+
+            while let Ok(command) = loop_rx.try_recv() {
+                self.handle_loop_command(
+                    command,
+                    target,
+                    &mut registry,
+                    &shared,
+                    &handler,
+                    control_flow,
+                );
+            }
+
+            let now = std::time::Instant::now();
+            for (label, native) in registry.take_native_events(now) {
+                self.forward_window_event(&registry, &label, &native);
             }
+            registry.flush_geometry(Some(now));
         });
+
+        registry.flush_geometry(None);
+        for responder in shared.clients.lock().values() {
+            responder.close();
+        }
+        Ok(())
+    }
+
+    /// Emit a native window event to the window's page and the Rust hook
+    fn forward_window_event(
+        &self,
+        registry: &WindowRegistry,
+        label: &str,
+        event: &NativeWindowEvent,
+    ) {
+        if let Some(hook) = &self.window_event_hook {
+            hook(label, event);
+        }
+        match taocket_event::emit_script(WINDOW_EVENT_TOPIC, event) {
+            Ok(script) => with_webview(registry, label, |webview| {
+                _ = webview.evaluate_script(&script);
+            }),
+            Err(e) => log::error!("Failed to serialize window event: {}", e),
+        }
+    }
+
+    /// Single path for every close: asks the close hook, then the page's
+    /// `beforeclose` listeners if it registered any, before closing
+    fn request_close(
+        &self,
+        label: &str,
+        registry: &mut WindowRegistry,
+        control_flow: &mut ControlFlow,
+    ) {
+        let Some(entry) = registry.get_mut(label) else {
+            log::warn!("Cannot close unknown window '{}'", label);
+            return;
+        };
+        let action = match &self.close_hook {
+            Some(hook) => hook(label, &entry.window),
+            None => CloseAction::Close,
+        };
+        match action {
+            CloseAction::Cancel => {}
+            CloseAction::Hide => entry.window.set_visible(false),
+            CloseAction::Close if entry.close_guard => {
+                // The page answers with `ResolveClose`; ignore repeats meanwhile
+                if !entry.closing {
+                    entry.closing = true;
+                    if let Some(ref webview) = *entry.webview.lock()
+                        && let Err(e) = webview.evaluate_script(REQUEST_CLOSE_SCRIPT)
+                    {
+                        log::error!("Failed to ask the page before closing: {}", e);
+                    }
+                }
+            }
+            CloseAction::Close => close_window(registry, label, control_flow),
+        }
+    }
+
+    fn handle_loop_command<F>(
+        &self,
+        command: LoopCommand,
+        target: &EventLoopWindowTarget<E>,
+        registry: &mut WindowRegistry,
+        shared: &Rc<IpcShared<E>>,
+        handler: &Rc<F>,
+        control_flow: &mut ControlFlow,
+    ) where
+        E: DeserializeOwned + Serialize,
+        F: Fn(Payload<E>, WindowContext<E>) + 'static,
+    {
+        match command {
+            LoopCommand::Respond(label, response) => {
+                deliver_response(registry, &label, &response);
+                shared.middleware.finish(&label, &response);
+            }
+            LoopCommand::RespondAll(label, responses) => {
+                with_webview(registry, &label, |webview| {
+                    if let Err(e) = taocket_ipc::send_responses(webview, &responses) {
+                        log::error!("Failed to deliver batched responses: {}", e);
+                    }
+                });
+                for response in &responses {
+                    shared.middleware.finish(&label, response);
+                }
+            }
+            LoopCommand::Script(window, script) => {
+                for entry in registry.targets(&window) {
+                    if let Some(ref webview) = *entry.webview.lock() {
+                        _ = webview.evaluate_script(&script);
+                    }
+                }
+            }
+            LoopCommand::Window(label, UserWindowEvent::Close)
+            | LoopCommand::CloseWindow(label) => {
+                self.request_close(&label, registry, control_flow);
+            }
+            LoopCommand::CloseGuard(label, enabled) => {
+                if let Some(entry) = registry.get_mut(&label) {
+                    entry.close_guard = enabled;
+                    entry.closing = false;
+                }
+            }
+            LoopCommand::ResolveClose(label, proceed) => {
+                let Some(entry) = registry.get_mut(&label) else {
+                    return;
+                };
+                if std::mem::take(&mut entry.closing) && proceed {
+                    close_window(registry, &label, control_flow);
+                }
+            }
+            LoopCommand::Window(label, event) => {
+                let Some(entry) = registry.get(&label) else {
+                    log::warn!("Window event for unknown window '{}'", label);
+                    return;
+                };
+                let window = &entry.window;
+                match event {
+                    UserWindowEvent::Minimize => window.set_minimized(true),
+                    UserWindowEvent::Maximize => window.set_maximized(true),
+                    UserWindowEvent::UnMaximize => window.set_maximized(false),
+                    UserWindowEvent::Focus => window.set_focus(),
+                    UserWindowEvent::Close => unreachable!("handled above"),
+                }
+            }
+            LoopCommand::OpenWindow { options, reply_to } => {
+                let label = options.label.clone();
+                let url = options.resolve_url(&self.start_url());
+                // Only windows opened from JS have a call to reply to
+                let result = if reply_to.is_some() && !is_app_origin(&url, &self.start_url()) {
+                    Err(format!(
+                        "Window '{}' can't load '{}' outside the app",
+                        label, url
+                    ))
+                } else {
+                    self.open_window(target, options, shared, handler, registry)
+                };
+                match reply_to {
+                    Some((from, id)) => {
+                        let response = match result {
+                            Ok(()) => IpcResponse::ok(id, serde_json::Value::String(label)),
+                            Err(e) => IpcResponse::err(id, IpcError::new(e)),
+                        };
+                        deliver_response(registry, &from, &response);
+                        shared.middleware.finish(&from, &response);
+                    }
+                    None => {
+                        if let Err(e) = result {
+                            log::error!("Failed to open window '{}': {}", label, e);
+                        }
+                    }
+                }
+            }
+        }
     }
 
     fn spawn_websocket_thread<W>(
@@ -473,12 +1613,12 @@ impl<A: AssetProvider + 'static, E: CustomEvent, X: CustomEvent> TaocketBuilder<
         websocket_clients: Clients,
         ws_handler: W,
         proxy: &EventLoopProxy<E>,
-    ) where
+    ) -> Result<(), ws::Error>
+    where
         W: Fn(u64, Message, &Clients, &EventLoopProxy<E>) + Send + 'static,
         E: CustomEvent,
     {
-        let event_hub =
-            ws::launch(self.attr.websocket_port).expect("Failed to launch WebSocket server");
+        let event_hub = ws::launch_with_limits(self.attr.websocket_port, self.config.limits)?;
         let event_proxy = proxy.clone();
 
         std::thread::spawn(move || {
@@ -496,10 +1636,11 @@ impl<A: AssetProvider + 'static, E: CustomEvent, X: CustomEvent> TaocketBuilder<
                 }
             }
         });
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
 #[serde(tag = "type", content = "data")]
 pub enum UserWindowEvent {
     Minimize,
@@ -509,7 +1650,68 @@ pub enum UserWindowEvent {
     Focus,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Dispatch state shared by the IPC handlers of every window
+struct IpcShared<E: CustomEvent> {
+    clients: Clients,
+    proxy: EventLoopProxy<E>,
+    loop_tx: Sender<LoopCommand>,
+    commands: CommandRouter<E>,
+    events: EventBus<E>,
+    capabilities: Vec<Capability>,
+    limits: Limits,
+    middleware: Arc<MiddlewareStack<E>>,
+    unhandled_hook: Option<UnhandledHook<E>>,
+    handshake_hook: Option<HandshakeHook<E>>,
+}
+
+/// Everything the wry IPC handler of one window needs to build contexts and
+/// dispatch calls
+struct IpcState<E: CustomEvent> {
+    shared: Rc<IpcShared<E>>,
+    window: Arc<Window>,
+    webview: WebviewContext,
+    loop_handle: LoopHandle,
+}
+
+impl<E: CustomEvent> IpcState<E> {
+    fn context(&self) -> WindowContext<E> {
+        WindowContext::from_proxy(
+            Arc::clone(&self.window),
+            Arc::clone(&self.webview),
+            Arc::clone(&self.shared.clients),
+            self.shared.proxy.clone(),
+        )
+        .with_loop_handle(self.loop_handle.clone())
+        .with_middleware(Arc::clone(&self.shared.middleware))
+        .with_events(self.shared.events.clone())
+    }
+
+    fn label(&self) -> &str {
+        &self.loop_handle.label
+    }
+}
+
+/// Work sent to the event loop from handlers, channels and other threads;
+/// `String` fields are window labels
+#[derive(Debug, Clone)]
+pub(crate) enum LoopCommand {
+    Respond(String, IpcResponse),
+    RespondAll(String, Vec<IpcResponse>),
+    Script(Target, String),
+    Window(String, UserWindowEvent),
+    /// Open a window, replying to call `reply_to` (label, id) if set
+    OpenWindow {
+        options: WindowOptions,
+        reply_to: Option<(String, i32)>,
+    },
+    CloseWindow(String),
+    /// Whether window `label` has `beforeclose` listeners to ask first
+    CloseGuard(String, bool),
+    /// Answer of the `beforeclose` listeners of window `label`
+    ResolveClose(String, bool),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
 #[serde(tag = "source", content = "payload")]
 pub enum TxEvent<X> {
     User(X),
@@ -540,57 +1742,440 @@ where
     pub fn send_window(&self, event: UserWindowEvent) {
         self.tx.send(TxEvent::Window(event)).unwrap();
     }
+
+    /// Emit `payload` to the JS listeners of `topic`
+    pub fn emit<T: Serialize>(&self, topic: &str, payload: T) -> Result<(), String> {
+        let script = taocket_event::emit_script(topic, &payload)?;
+        self.tx
+            .send(TxEvent::Script(script))
+            .map_err(|_| "Event loop is not running".to_string())
+    }
 }
 
 // ============================================================================
 // Utility Functions
 // ============================================================================
 
+/// Scheme of the custom protocol serving assets and binary commands
+pub const PROTOCOL_NAME: &str = "taocket";
+
+/// DOM event carrying user events `E` sent through the event loop proxy
+pub const USER_EVENT_NAME: &str = "taocket:websocket|event";
+/// DOM event carrying `X` values sent with `Dispatcher::send_user`
+pub const DISPATCH_EVENT_NAME: &str = "taocket:dispatch|event";
+
+pub(crate) fn dispatch_event_script(name: &str, detail_json: &str) -> String {
+    format!(
+        "window.dispatchEvent(new CustomEvent('{}', {{ detail: {} }}));",
+        name, detail_json
+    )
+}
+
+/// `init.js` with the backend's protocol version filled in
+fn init_script() -> String {
+    include_str!("scripts/init.js").replace("__TAOCKET_PROTOCOL__", &PROTOCOL_VERSION.to_string())
+}
+
+/// Load a window icon (`.ico` on Windows)
+#[cfg(target_os = "windows")]
+fn load_icon(path: &std::path::Path) -> Result<Icon, String> {
+    use tao::platform::windows::IconExtWindows;
+    Icon::from_path(path, None).map_err(|e| e.to_string())
+}
+
+/// Load a window icon from any image format gdk-pixbuf reads
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn load_icon(path: &std::path::Path) -> Result<Icon, String> {
+    let pixbuf = gdk_pixbuf::Pixbuf::from_file(path)
+        .map_err(|e| e.to_string())?
+        .add_alpha(false, 0, 0, 0)
+        .map_err(|e| e.to_string())?;
+    let (width, height) = (pixbuf.width() as usize, pixbuf.height() as usize);
+    let stride = pixbuf.rowstride() as usize;
+    let pixels = pixbuf.read_pixel_bytes();
+    // Rows are padded to `stride`; the icon wants them packed
+    let rgba = (0..height)
+        .flat_map(|row| &pixels[row * stride..row * stride + width * 4])
+        .copied()
+        .collect();
+    Icon::from_rgba(rgba, width as u32, height as u32).map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+fn load_icon(_path: &std::path::Path) -> Result<Icon, String> {
+    Err("window icons are not supported on this platform".to_string())
+}
+
+/// Script telling the page which window it runs in, how wide its automatic
+/// resize border is and what double-clicking a drag region does, read by
+/// `init.js` and `dragevent.js`
+fn window_script(label: &str, resize_border: f64, double_click: DoubleClickAction) -> String {
+    format!(
+        "window.__TAOCKET_LABEL__ = {}; window.__TAOCKET_RESIZE_BORDER__ = {}; \
+         window.__TAOCKET_DOUBLE_CLICK__ = \"{}\";",
+        serde_json::Value::from(label),
+        serde_json::Value::from(resize_border),
+        double_click.as_str()
+    )
+}
+
+/// Whether the invisible resize border of `window` is live: it must be
+/// frameless, resizable and not maximized
+fn resize_border_enabled(window: &Window) -> bool {
+    !window.is_decorated() && window.is_resizable() && !window.is_maximized()
+}
+
+fn resize_border_script(window: &Window) -> String {
+    format!(
+        "window.__TAOCKET_RESIZE_ENABLED__ = {};",
+        resize_border_enabled(window)
+    )
+}
+
+/// Tell the page whether its resize border is live
+fn push_resize_border(window: &Window, webview: &WebviewContext) {
+    if let Some(ref webview) = *webview.lock()
+        && let Err(e) = webview.evaluate_script(&resize_border_script(window))
+    {
+        log::warn!("Failed to update the resize border: {}", e);
+    }
+}
+
+/// Kind of a message; kind-less messages come from frontends predating
+/// `kind`, whose built-in window commands still reach the built-ins
+fn message_kind(kind: Option<IpcKind>, event: Option<&str>) -> IpcKind {
+    match kind {
+        Some(kind) => kind,
+        None if event.is_some_and(|event| {
+            taocket_ipc::check_event::<InternalWindowEvent>(event).is_ok()
+        }) =>
+        {
+            IpcKind::Window
+        }
+        None => IpcKind::Invoke,
+    }
+}
+
+/// Decode a built-in window command; `legacy` (kind-less) messages may carry
+/// its data under the old `data` key
+fn decode_window_message(
+    body: &str,
+    legacy: bool,
+) -> Result<IpcMessage<InternalWindowEvent>, IpcError> {
+    if !legacy {
+        return taocket_ipc::decode_str(body);
+    }
+    let mut message = taocket_ipc::decode_str::<serde_json::Value>(body)?;
+    if let Some(event) = message
+        .pointer_mut("/payload/event")
+        .and_then(serde_json::Value::as_object_mut)
+        && !event.contains_key("value")
+        && let Some(data) = event.remove("data")
+    {
+        event.insert("value".to_string(), data);
+    }
+    taocket_ipc::decode_value(message)
+}
+
+/// Built-in window commands from frontends predating `kind`; sizes and
+/// positions are read in physical pixels, as the old `WindowControl` did
+fn handle_legacy_window_event<E: CustomEvent>(
+    payload: Payload<InternalWindowEvent>,
+    context: &WindowContext<E>,
+) {
+    let window = context.window();
+    let value = match payload.event {
+        InternalWindowEvent::GetSize => {
+            let size = window.inner_size();
+            serde_json::json!({ "width": size.width, "height": size.height })
+        }
+        InternalWindowEvent::GetPosition => {
+            let position = window.outer_position().unwrap_or_default();
+            serde_json::json!({ "x": position.x, "y": position.y })
+        }
+        _ => return handle_internal_window_event(payload, context),
+    };
+    if let Err(e) = context.respond(IpcResponse::ok(payload.id, value)) {
+        log::error!("Failed to reply to internal window event: {}", e);
+    }
+}
+
+/// Whether every call of `kind` gets a reply, so middleware can wait for it;
+/// user events handled by the `run` handler need not be answered
+fn always_settles(kind: IpcKind, is_command: bool) -> bool {
+    match kind {
+        IpcKind::Window | IpcKind::Emit => true,
+        IpcKind::Invoke => is_command,
+        IpcKind::Cancel | IpcKind::Handshake | IpcKind::Batch => false,
+    }
+}
+
+/// Call id and event name of a raw message, read without decoding the payload
+fn call_target(body: &str) -> (Option<i32>, Option<String>) {
+    let Ok(envelope) = serde_json::from_str::<serde_json::Value>(body) else {
+        return (None, None);
+    };
+    let id = envelope
+        .pointer("/payload/id")
+        .and_then(serde_json::Value::as_i64)
+        .and_then(|id| i32::try_from(id).ok());
+    let event = envelope
+        .pointer("/payload/event/type")
+        .and_then(serde_json::Value::as_str)
+        .map(str::to_string);
+    (id, event)
+}
+
 pub fn broadcast_message(clients: &Clients, message: String) {
     for (_, responder) in clients.lock().iter() {
         let _ = responder.send(Message::Text(message.clone()));
     }
 }
 
+// ============================================================================
+// Loop Command Handler
+// ============================================================================
+
+/// Run `f` with the webview of window `label`, if it is still open
+fn with_webview(registry: &WindowRegistry, label: &str, f: impl FnOnce(&wry::WebView)) {
+    if let Some(entry) = registry.get(label)
+        && let Some(ref webview) = *entry.webview.lock()
+    {
+        f(webview);
+    }
+}
+
+fn deliver_response(registry: &WindowRegistry, label: &str, response: &IpcResponse) {
+    with_webview(registry, label, |webview| {
+        if let Err(e) = taocket_ipc::send_response(webview, response) {
+            log::error!("Failed to deliver async response: {}", e);
+        }
+    });
+}
+
+/// Runs the page's `beforeclose` listeners, see `init.js`
+const REQUEST_CLOSE_SCRIPT: &str = "window.__TAOCKET__.requestClose();";
+
+/// Close window `label` without asking; closing the main window exits the
+/// event loop
+fn close_window(registry: &mut WindowRegistry, label: &str, control_flow: &mut ControlFlow) {
+    if label == MAIN_WINDOW {
+        *control_flow = ControlFlow::Exit;
+    } else if !registry.remove(label) {
+        log::warn!("Cannot close unknown window '{}'", label);
+    }
+}
+
 // ============================================================================
 // Internal Window Event Handler
 // ============================================================================
 
-fn handle_internal_window_event(
+fn handle_internal_window_event<E: CustomEvent>(
     payload: Payload<InternalWindowEvent>,
-    window: &Window,
-    webview: &WebView,
+    context: &WindowContext<E>,
 ) {
-    match payload.event {
-        InternalWindowEvent::Minimize => window.set_minimized(true),
+    let id = payload.id;
+    let result = match payload.event {
+        InternalWindowEvent::Close => {
+            if let Err(e) = context.respond(IpcResponse::ok(id, serde_json::Value::Null)) {
+                log::error!("Failed to reply to internal window event: {}", e);
+            }
+            // Goes through the close hook and `beforeclose` like a native close
+            if let Err(e) = context.close_window(context.label()) {
+                log::error!("Failed to close window: {}", e);
+            }
+            return;
+        }
+        InternalWindowEvent::GuardClose(enabled) => context
+            .loop_handle()
+            .and_then(|handle| {
+                handle.send(LoopCommand::CloseGuard(
+                    context.label().to_string(),
+                    enabled,
+                ))
+            })
+            .map(|()| serde_json::Value::Null)
+            .map_err(IpcError::new),
+        InternalWindowEvent::ResolveClose(proceed) => context
+            .loop_handle()
+            .and_then(|handle| {
+                handle.send(LoopCommand::ResolveClose(
+                    context.label().to_string(),
+                    proceed,
+                ))
+            })
+            .map(|()| serde_json::Value::Null)
+            .map_err(IpcError::new),
+        InternalWindowEvent::OpenWindow(options) => {
+            // The event loop replies once the window exists
+            let sent = context.loop_handle().and_then(|handle| {
+                handle.send(LoopCommand::OpenWindow {
+                    options,
+                    reply_to: Some((context.label().to_string(), id)),
+                })
+            });
+            match sent {
+                Ok(()) => return,
+                Err(e) => Err(IpcError::new(e)),
+            }
+        }
+        InternalWindowEvent::CloseWindow(label) => context
+            .close_window(label)
+            .map(|()| serde_json::Value::Null)
+            .map_err(IpcError::new),
+        event @ (InternalWindowEvent::SetResizable(_) | InternalWindowEvent::SetDecorations(_)) => {
+            let result = apply_window_event(event, context.window());
+            push_resize_border(context.window(), context.webview());
+            result
+        }
+        event => apply_window_event(event, context.window()),
+    };
+
+    let response = match result {
+        Ok(value) => IpcResponse::ok(id, value),
+        Err(error) => IpcResponse::err(id, error),
+    };
+    if let Err(e) = context.respond(response) {
+        log::error!("Failed to reply to internal window event: {}", e);
+    }
+}
+
+/// Run a built-in command against `window`; sizes and positions are logical
+fn apply_window_event(
+    event: InternalWindowEvent,
+    window: &Window,
+) -> Result<serde_json::Value, IpcError> {
+    use serde_json::{Value, json};
+
+    let scale = window.scale_factor();
+    let size_value = |size: PhysicalSize<u32>| {
+        let size = size.to_logical::<f64>(scale);
+        json!({ "width": size.width, "height": size.height })
+    };
+    let logical = |size: WindowSize| LogicalSize::new(size.width, size.height);
+
+    let value = match event {
+        InternalWindowEvent::Minimize => {
+            window.set_minimized(true);
+            Value::Null
+        }
         InternalWindowEvent::Maximize => {
             let is_maximized = window.is_maximized();
             window.set_maximized(!is_maximized);
+            Value::Null
+        }
+        InternalWindowEvent::UnMaximize => {
+            window.set_maximized(false);
+            Value::Null
         }
-        InternalWindowEvent::UnMaximize => window.set_maximized(false),
-        InternalWindowEvent::Close => std::process::exit(0),
         InternalWindowEvent::Move => {
             let _ = window.drag_window();
+            Value::Null
         }
-        InternalWindowEvent::Focus => window.set_focus(),
-        InternalWindowEvent::IsMaximized => {
-            send_window_state_response(webview, &payload.event, window.is_maximized());
+        InternalWindowEvent::StartResizing(edge) => {
+            if !window.is_resizable() || window.is_maximized() {
+                return Ok(Value::Null);
+            }
+            window
+                .drag_resize_window(edge.into())
+                .map_err(|e| IpcError::new(format!("Cannot resize window: {}", e)))?;
+            Value::Null
         }
-        InternalWindowEvent::IsMinimized => {
-            send_window_state_response(webview, &payload.event, window.is_minimized());
+        InternalWindowEvent::Focus => {
+            window.set_focus();
+            Value::Null
         }
-        InternalWindowEvent::IsFocus => {
-            send_window_state_response(webview, &payload.event, window.is_focused());
+        InternalWindowEvent::IsMaximized => Value::Bool(window.is_maximized()),
+        InternalWindowEvent::IsMinimized => Value::Bool(window.is_minimized()),
+        InternalWindowEvent::IsFocus => Value::Bool(window.is_focused()),
+        InternalWindowEvent::GetSize => size_value(window.inner_size()),
+        InternalWindowEvent::SetSize { width, height } => {
+            window.set_inner_size(LogicalSize::new(width, height));
+            Value::Null
+        }
+        InternalWindowEvent::GetOuterSize => size_value(window.outer_size()),
+        InternalWindowEvent::SetOuterSize { width, height } => {
+            // tao only sizes the client area, so subtract the frame
+            let inner = window.inner_size().to_logical::<f64>(scale);
+            let outer = window.outer_size().to_logical::<f64>(scale);
+            window.set_inner_size(LogicalSize::new(
+                (width - (outer.width - inner.width)).max(1.0),
+                (height - (outer.height - inner.height)).max(1.0),
+            ));
+            Value::Null
+        }
+        InternalWindowEvent::SetMinSize(size) => {
+            window.set_min_inner_size(size.map(logical));
+            Value::Null
+        }
+        InternalWindowEvent::SetMaxSize(size) => {
+            window.set_max_inner_size(size.map(logical));
+            Value::Null
+        }
+        InternalWindowEvent::GetPosition => {
+            let position = window
+                .outer_position()
+                .map_err(|e| IpcError::new(format!("Window position unavailable: {}", e)))?
+                .to_logical::<f64>(scale);
+            json!({ "x": position.x, "y": position.y })
+        }
+        InternalWindowEvent::SetPosition { x, y } => {
+            window.set_outer_position(LogicalPosition::new(x, y));
+            Value::Null
+        }
+        InternalWindowEvent::Center => {
+            let monitor = window
+                .current_monitor()
+                .ok_or_else(|| IpcError::new("Window is not on a monitor"))?;
+            let (origin, area, size) = (monitor.position(), monitor.size(), window.outer_size());
+            window.set_outer_position(PhysicalPosition::new(
+                origin.x + (area.width as i32 - size.width as i32) / 2,
+                origin.y + (area.height as i32 - size.height as i32) / 2,
+            ));
+            Value::Null
+        }
+        InternalWindowEvent::SetVisible(visible) => {
+            window.set_visible(visible);
+            Value::Null
+        }
+        InternalWindowEvent::IsVisible => Value::Bool(window.is_visible()),
+        InternalWindowEvent::SetResizable(enabled) => {
+            window.set_resizable(enabled);
+            Value::Null
+        }
+        InternalWindowEvent::IsResizable => Value::Bool(window.is_resizable()),
+        InternalWindowEvent::GetTitle => Value::String(window.title()),
+        InternalWindowEvent::SetTitle(title) => {
+            window.set_title(&title);
+            Value::Null
+        }
+        InternalWindowEvent::ScaleFactor => json!(scale),
+        InternalWindowEvent::SetDecorations(enabled) => {
+            window.set_decorations(enabled);
+            Value::Null
+        }
+        InternalWindowEvent::Close
+        | InternalWindowEvent::OpenWindow(_)
+        | InternalWindowEvent::CloseWindow(_)
+        | InternalWindowEvent::GuardClose(_)
+        | InternalWindowEvent::ResolveClose(_) => {
+            unreachable!("handled by handle_internal_window_event")
         }
-    }
-}
-
-fn send_window_state_response(webview: &WebView, event: &InternalWindowEvent, state: bool) {
-    let payload = WindowAttrPayload {
-        attr_type: event.as_str(),
-        value: serde_json::Value::Bool(state),
     };
-    callback!(webview, event.to_str_response(), payload);
+    Ok(value)
 }
 
 // ============================================================================
@@ -604,10 +2189,12 @@ mod tests {
     #[test]
     fn test_ipc_message_serialization() {
         let message = IpcMessage {
+            kind: IpcKind::Window,
             payload: Payload {
                 id: 1,
                 event: InternalWindowEvent::Close,
                 value: Some(serde_json::Value::Bool(true)),
+                event_value: None,
             },
         };
 
@@ -617,15 +2204,268 @@ mod tests {
         assert_eq!(message, deserialized);
     }
 
+    #[test]
+    fn test_window_event_values() {
+        let event: InternalWindowEvent =
+            serde_json::from_str(r#"{"type":"SetSize","value":{"width":800,"height":600}}"#)
+                .unwrap();
+        assert_eq!(
+            event,
+            InternalWindowEvent::SetSize {
+                width: 800.0,
+                height: 600.0
+            }
+        );
+
+        let event: InternalWindowEvent =
+            serde_json::from_str(r#"{"type":"SetDecorations","value":false}"#).unwrap();
+        assert_eq!(event, InternalWindowEvent::SetDecorations(false));
+
+        let event: InternalWindowEvent = serde_json::from_str(r#"{"type":"GetSize"}"#).unwrap();
+        assert_eq!(event, InternalWindowEvent::GetSize);
+
+        let event: InternalWindowEvent =
+            serde_json::from_str(r#"{"type":"SetMinSize","value":{"width":200,"height":100}}"#)
+                .unwrap();
+        assert_eq!(
+            event,
+            InternalWindowEvent::SetMinSize(Some(WindowSize::from((200.0, 100.0))))
+        );
+
+        let event: InternalWindowEvent =
+            serde_json::from_str(r#"{"type":"SetMaxSize","value":null}"#).unwrap();
+        assert_eq!(event, InternalWindowEvent::SetMaxSize(None));
+
+        let event: InternalWindowEvent =
+            serde_json::from_str(r#"{"type":"SetTitle","value":"Launcher"}"#).unwrap();
+        assert_eq!(event, InternalWindowEvent::SetTitle("Launcher".into()));
+
+        let event: InternalWindowEvent =
+            serde_json::from_str(r#"{"type":"StartResizing","value":"nw"}"#).unwrap();
+        assert_eq!(event, InternalWindowEvent::StartResizing(ResizeEdge::Nw));
+
+        let event: InternalWindowEvent =
+            serde_json::from_str(r#"{"type":"ResolveClose","value":false}"#).unwrap();
+        assert_eq!(event, InternalWindowEvent::ResolveClose(false));
+
+        let event: InternalWindowEvent = serde_json::from_str(
+            r#"{"type":"OpenWindow","value":{"label":"settings","url":"/settings.html"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            event,
+            InternalWindowEvent::OpenWindow(WindowOptions::new("settings").url("/settings.html"))
+        );
+    }
+
+    #[test]
+    fn test_window_script() {
+        assert_eq!(
+            window_script("a\"b", 5.0, DoubleClickAction::None),
+            r#"window.__TAOCKET_LABEL__ = "a\"b"; window.__TAOCKET_RESIZE_BORDER__ = 5.0; window.__TAOCKET_DOUBLE_CLICK__ = "none";"#
+        );
+    }
+
+    #[test]
+    fn test_ipc_kind_defaults_to_invoke() {
+        let body = r#"{"payload":{"id":1,"event":{"type":"save"}}}"#;
+        let envelope: IpcEnvelope = serde_json::from_str(body).unwrap();
+        assert_eq!(envelope.kind, None);
+        assert_eq!(message_kind(envelope.kind, Some("save")), IpcKind::Invoke);
+
+        let body = r#"{"kind":"emit","payload":{"id":1,"event":{"type":"saved"}}}"#;
+        let envelope: IpcEnvelope = serde_json::from_str(body).unwrap();
+        assert_eq!(envelope.kind, Some(IpcKind::Emit));
+
+        // An explicit kind keeps user events apart from built-ins
+        assert_eq!(
+            message_kind(Some(IpcKind::Invoke), Some("Close")),
+            IpcKind::Invoke
+        );
+
+        let body = r#"{"kind":"window","payload":{"id":1,"event":{"type":"Close"}}}"#;
+        let msg: IpcMessage<InternalWindowEvent> = serde_json::from_str(body).unwrap();
+        assert_eq!(msg.kind, IpcKind::Window);
+        assert_eq!(msg.payload.event, InternalWindowEvent::Close);
+    }
+
+    #[test]
+    fn test_legacy_window_message() {
+        // As sent by the baseline `invoke` and `WindowControlMessage`
+        for body in [
+            r#"{"payload":{"id":3,"event":{"type":"SetSize","value":{"width":800,"height":600}}}}"#,
+            r#"{"payload":{"id":3,"event":{"type":"SetSize","data":{"width":800,"height":600}}}}"#,
+        ] {
+            let envelope: IpcEnvelope = serde_json::from_str(body).unwrap();
+            let (id, name) = call_target(body);
+            assert_eq!(id, Some(3));
+            assert_eq!(
+                message_kind(envelope.kind, name.as_deref()),
+                IpcKind::Window
+            );
+            let msg = decode_window_message(body, envelope.kind.is_none()).unwrap();
+            assert_eq!(
+                msg.payload.event,
+                InternalWindowEvent::SetSize {
+                    width: 800.0,
+                    height: 600.0
+                }
+            );
+        }
+
+        for event in ["GetSize", "GetPosition", "Minimize", "Close"] {
+            assert_eq!(message_kind(None, Some(event)), IpcKind::Window);
+        }
+        let body = r#"{"payload":{"id":4,"event":{"type":"SetPosition","value":{"x":10,"y":20}}}}"#;
+        assert_eq!(
+            decode_window_message(body, true).unwrap().payload.event,
+            InternalWindowEvent::SetPosition { x: 10.0, y: 20.0 }
+        );
+    }
+
+    #[test]
+    fn test_malformed_batch_rejects_its_calls() {
+        let error = IpcError::with_kind(IpcErrorKind::InvalidMessage, "bad batch");
+        // No id of its own, and a member that isn't a message
+        let body = r#"{"kind":"batch","payload":{"event":{"type":"Batch","value":[
+            {"kind":"invoke","payload":{"id":7,"event":{"type":"save"}}},
+            3,
+            {"kind":"invoke","payload":{"id":8,"event":{"type":"load","value":[1,2]}}}
+        ]}}}"#;
+        assert!(taocket_ipc::decode_str::<IpcMessage<BatchCalls>>(body).is_err());
+        let responses = member_rejections(body, &error);
+        assert_eq!(
+            responses.iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![7, 8]
+        );
+        assert!(
+            responses
+                .iter()
+                .all(|r| r.result == IpcResult::Err(error.clone()))
+        );
+
+        let single = r#"{"kind":"invoke","payload":{"id":1,"event":{"type":"Batch","value":[{"payload":{"id":2}}]}}}"#;
+        assert!(member_rejections(single, &error).is_empty());
+        assert!(member_rejections("not json", &error).is_empty());
+    }
+
+    #[test]
+    fn test_batch_calls() {
+        let body = r#"{"kind":"batch","payload":{"id":9,"event":{"type":"Batch","value":[
+            {"kind":"invoke","payload":{"id":1,"event":{"type":"load"}}},
+            {"kind":"window","payload":{"id":2,"event":{"type":"IsMaximized"}}}
+        ]}}}"#;
+        let msg: IpcMessage<BatchCalls> = serde_json::from_str(body).unwrap();
+        assert_eq!(msg.kind, IpcKind::Batch);
+        let calls = msg.payload.event.value;
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            call_target(calls[1].get()),
+            (Some(2), Some("IsMaximized".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_only_settling_calls_are_tracked() {
+        assert!(always_settles(IpcKind::Window, false));
+        assert!(always_settles(IpcKind::Emit, false));
+        assert!(always_settles(IpcKind::Invoke, true));
+        // User events may never be answered by the `run` handler
+        assert!(!always_settles(IpcKind::Invoke, false));
+        assert!(!always_settles(IpcKind::Cancel, false));
+    }
+
+    #[test]
+    fn test_call_target() {
+        let body = r#"{"kind":"window","payload":{"id":4,"event":{"type":"Close"}}}"#;
+        assert_eq!(call_target(body), (Some(4), Some("Close".to_string())));
+        assert_eq!(call_target("{not json"), (None, None));
+    }
+
+    #[test]
+    fn test_internal_window_commands() {
+        // `onBeforeClose` in init.js relies on these passing any capability set
+        let init = include_str!("scripts/init.js");
+        assert!(init.contains(r#"post("window", "GuardClose""#));
+        assert!(init.contains(r#"post("window", "ResolveClose""#));
+        for name in taocket_capability::INTERNAL_WINDOW_COMMANDS
+            .iter()
+            .chain(taocket_capability::DRAG_REGION_COMMANDS)
+        {
+            assert!(taocket_ipc::check_event::<InternalWindowEvent>(name).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_init_script_protocol_version() {
+        let script = init_script();
+        assert!(!script.contains("__TAOCKET_PROTOCOL__"));
+        assert!(script.contains(&format!("const PROTOCOL_VERSION = {};", PROTOCOL_VERSION)));
+    }
+
     #[test]
     fn test_payload_without_value() {
         let payload: Payload<InternalWindowEvent> = Payload {
             id: 42,
             event: InternalWindowEvent::Minimize,
             value: None,
+            event_value: None,
         };
 
         let json = serde_json::to_string(&payload).unwrap();
         assert!(!json.contains("value"));
     }
+
+    #[test]
+    fn test_payload_value_extraction() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Rename {
+            name: String,
+        }
+
+        let payload = |value| Payload {
+            id: 1,
+            event: InternalWindowEvent::Move,
+            value,
+            event_value: None,
+        };
+
+        let named = payload(Some(serde_json::json!({"name": "notes.txt"})));
+        assert_eq!(named.required::<Rename>().unwrap().name, "notes.txt");
+        assert!(named.optional::<Rename>().unwrap().is_some());
+
+        let missing = payload(None);
+        assert_eq!(missing.value_as::<Option<Rename>>().unwrap(), None);
+        assert_eq!(missing.optional::<Rename>().unwrap(), None);
+        let err = missing.required::<Rename>().unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::InvalidPayload);
+        assert_eq!(err.path.as_deref(), Some("value"));
+
+        let wrong = payload(Some(serde_json::json!({"name": 3})));
+        let err = wrong.value_as::<Rename>().unwrap_err();
+        assert_eq!(err.kind, IpcErrorKind::InvalidPayload);
+        assert_eq!(err.path.as_deref(), Some("value.name"));
+
+        let list = payload(Some(serde_json::json!([{"name": 3}])));
+        let err = list.required::<Vec<Rename>>().unwrap_err();
+        assert_eq!(err.path.as_deref(), Some("value[0].name"));
+
+        // Without `payload.value` the helpers read the event's value as
+        // sent, even when the event type drops it
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(tag = "type")]
+        enum Event {
+            Rename,
+        }
+
+        let body = r#"{"payload":{"id":1,"event":{"type":"Rename","value":{"name":"notes.txt"}}}}"#;
+        let msg = decode_user_message::<Event>(body).unwrap();
+        assert_eq!(msg.payload.event, Event::Rename);
+        assert!(msg.payload.value.is_none());
+        assert_eq!(msg.payload.required::<Rename>().unwrap().name, "notes.txt");
+
+        let body = r#"{"payload":{"id":2,"event":{"type":"Rename"}}}"#;
+        let msg = decode_user_message::<Event>(body).unwrap();
+        assert_eq!(msg.payload.optional::<Rename>().unwrap(), None);
+    }
 }
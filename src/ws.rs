@@ -1,8 +1,13 @@
 use futures_util::{SinkExt, StreamExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::runtime::Runtime;
-use tokio_tungstenite::tungstenite::{Bytes, Utf8Bytes};
-use tokio_tungstenite::{accept_async, tungstenite};
+use tokio_tungstenite::tungstenite::Utf8Bytes;
+use tokio_tungstenite::tungstenite::protocol::{
+    CloseFrame, WebSocketConfig, frame::coding::CloseCode,
+};
+use tokio_tungstenite::{accept_async_with_config, tungstenite};
+
+use crate::taocket_limits::{self, Limits};
 
 #[derive(Debug)]
 pub enum Error {
@@ -32,12 +37,9 @@ impl Message {
 enum ResponderCommand {
     Message(Message),
     CloseConnection,
+    CloseWith(CloseFrame),
 }
 
-///
-
-///
-
 #[derive(Debug, Clone)]
 pub struct Responder {
     tx: flume::Sender<ResponderCommand>,
@@ -49,16 +51,10 @@ impl Responder {
         Self { tx, client_id }
     }
 
-    ///
-
-    ///
-
     pub fn send(&self, message: Message) -> bool {
         self.tx.send(ResponderCommand::Message(message)).is_ok()
     }
 
-    ///
-
     pub fn close(&self) {
         let _ = self.tx.send(ResponderCommand::CloseConnection);
     }
@@ -77,8 +73,6 @@ pub enum Event {
     Message(u64, Message),
 }
 
-///
-
 #[derive(Debug)]
 pub struct EventHub {
     rx: flume::Receiver<Event>,
@@ -117,21 +111,28 @@ impl EventHub {
 }
 
 pub fn launch(port: u16) -> Result<EventHub, Error> {
+    launch_with_limits(port, Limits::default())
+}
+
+pub fn launch_with_limits(port: u16, limits: Limits) -> Result<EventHub, Error> {
     let address = format!("0.0.0.0:{}", port);
     let listener = std::net::TcpListener::bind(&address).map_err(|_| Error::FailedToStart)?;
-    return launch_from_listener(listener);
+    launch_from_listener_with_limits(listener, limits)
 }
 
-///
-
-///
-
 pub fn launch_from_listener(listener: std::net::TcpListener) -> Result<EventHub, Error> {
+    launch_from_listener_with_limits(listener, Limits::default())
+}
+
+pub fn launch_from_listener_with_limits(
+    listener: std::net::TcpListener,
+    limits: Limits,
+) -> Result<EventHub, Error> {
     let (tx, rx) = flume::unbounded();
     std::thread::Builder::new()
         .name("Websocket listener".to_string())
         .spawn(move || {
-            start_runtime(tx, listener).unwrap();
+            start_runtime(tx, listener, limits).unwrap();
         })
         .map_err(|_| Error::FailedToStart)?;
 
@@ -141,6 +142,7 @@ pub fn launch_from_listener(listener: std::net::TcpListener) -> Result<EventHub,
 fn start_runtime(
     event_tx: flume::Sender<Event>,
     listener: std::net::TcpListener,
+    limits: Limits,
 ) -> Result<(), Error> {
     listener
         .set_nonblocking(true)
@@ -151,19 +153,43 @@ fn start_runtime(
             let tokio_listener = TcpListener::from_std(listener).unwrap();
             let mut current_id: u64 = 0;
             loop {
-                match tokio_listener.accept().await {
-                    Ok((stream, _)) => {
-                        tokio::spawn(handle_connection(stream, event_tx.clone(), current_id));
-                        current_id = current_id.wrapping_add(1);
-                    }
-                    _ => {}
+                if let Ok((stream, _)) = tokio_listener.accept().await {
+                    tokio::spawn(handle_connection(
+                        stream,
+                        event_tx.clone(),
+                        current_id,
+                        limits,
+                    ));
+                    current_id = current_id.wrapping_add(1);
                 }
             }
         })
 }
 
-async fn handle_connection(stream: TcpStream, event_tx: flume::Sender<Event>, id: u64) {
-    let ws_stream = match accept_async(stream).await {
+/// Close frame sent to a client that broke one of the `Limits`
+fn limit_close_frame(reason: impl Into<String>) -> CloseFrame {
+    CloseFrame {
+        code: CloseCode::Size,
+        reason: Utf8Bytes::from(reason.into()),
+    }
+}
+
+/// Whether a text message is JSON nested deeper than the limit allows
+fn exceeds_depth(text: &str, limits: &Limits) -> bool {
+    text.trim_start().starts_with(['{', '['])
+        && taocket_limits::exceeds_depth(text, limits.max_json_depth)
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    event_tx: flume::Sender<Event>,
+    id: u64,
+    limits: Limits,
+) {
+    let config = WebSocketConfig::default()
+        .max_message_size(Some(limits.max_ws_message_size))
+        .max_frame_size(Some(limits.max_ws_message_size));
+    let ws_stream = match accept_async_with_config(stream, Some(config)).await {
         Ok(s) => s,
         Err(_) => return,
     };
@@ -172,6 +198,7 @@ async fn handle_connection(stream: TcpStream, event_tx: flume::Sender<Event>, id
 
     // channel for the `Responder` to send things to this websocket
     let (resp_tx, resp_rx) = flume::unbounded();
+    let close_tx = resp_tx.clone();
 
     event_tx
         .send(Event::Connect(id, Responder::new(resp_tx, id)))
@@ -182,7 +209,7 @@ async fn handle_connection(stream: TcpStream, event_tx: flume::Sender<Event>, id
         while let Ok(event) = resp_rx.recv_async().await {
             match event {
                 ResponderCommand::Message(message) => {
-                    if let Err(_) = outgoing.send(message.into_tungstenite()).await {
+                    if outgoing.send(message.into_tungstenite()).await.is_err() {
                         let _ = outgoing.close().await;
                         return Ok(());
                     }
@@ -191,6 +218,13 @@ async fn handle_connection(stream: TcpStream, event_tx: flume::Sender<Event>, id
                     let _ = outgoing.close().await;
                     return Ok(());
                 }
+                ResponderCommand::CloseWith(frame) => {
+                    let _ = outgoing
+                        .send(tungstenite::Message::Close(Some(frame)))
+                        .await;
+                    let _ = outgoing.close().await;
+                    return Ok(());
+                }
             }
         }
 
@@ -205,12 +239,32 @@ async fn handle_connection(stream: TcpStream, event_tx: flume::Sender<Event>, id
     //future that forwards messages received from the websocket to the event channel
     let events = async move {
         while let Some(message) = incoming.next().await {
-            if let Ok(tungstenite_msg) = message {
-                if let Some(msg) = Message::from_tungstenite(tungstenite_msg) {
-                    event_tx2
-                        .send(Event::Message(id, msg))
-                        .expect("Parent thread is dead");
+            let tungstenite_msg = match message {
+                Ok(tungstenite_msg) => tungstenite_msg,
+                // Oversized messages are refused by tungstenite before they are buffered;
+                // returning Ok lets responder_events send the close frame before stopping
+                Err(tungstenite::Error::Capacity(e)) => {
+                    log::warn!("Closing WebSocket client {}: {}", id, e);
+                    let _ = close_tx.send(ResponderCommand::CloseWith(limit_close_frame(
+                        e.to_string(),
+                    )));
+                    return Ok(());
+                }
+                Err(_) => continue,
+            };
+            if let Some(msg) = Message::from_tungstenite(tungstenite_msg) {
+                let Message::Text(text) = &msg;
+                if exceeds_depth(text, &limits) {
+                    log::warn!("Closing WebSocket client {}: message nested too deeply", id);
+                    let _ = close_tx.send(ResponderCommand::CloseWith(limit_close_frame(format!(
+                        "JSON nested deeper than {} levels",
+                        limits.max_json_depth
+                    ))));
+                    return Ok(());
                 }
+                event_tx2
+                    .send(Event::Message(id, msg))
+                    .expect("Parent thread is dead");
             }
         }
 